@@ -0,0 +1,239 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use hmac::{Hmac, Mac};
+use rusqlite::{params, Connection, Error, OpenFlags, Result as SQLiteResult, NO_PARAMS};
+use sha2::Sha256;
+
+const OBSERVED_DOMAINS: &str = "SELECT domainID, registrableDomain FROM ObservedDomains";
+const RENAME_DOMAIN: &str = "UPDATE ObservedDomains SET registrableDomain = ? WHERE domainID = ?";
+const USER_TABLES: &str =
+    "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'";
+
+/// Tables describing ITP's view of the world, they only reference domains by id.
+const ITP_TABLES: &[&str] = &[
+    "ObservedDomains",
+    "TopLevelDomains",
+    "StorageAccessUnderTopFrameDomains",
+    "TopFrameUniqueRedirectsTo",
+    "TopFrameUniqueRedirectsToSinceSameSiteStrictEnforcement",
+    "TopFrameUniqueRedirectsFrom",
+    "TopFrameLinkDecorationsFrom",
+    "TopFrameLoadedThirdPartyScripts",
+    "SubframeUnderTopFrameDomains",
+    "SubresourceUnderTopFrameDomains",
+    "SubresourceUniqueRedirectsTo",
+    "SubresourceUniqueRedirectsFrom",
+    "OperatingDates",
+];
+
+/// Suffix of every hashed domain, reserved so it never collides with a real site.
+const ANONYMIZED_SUFFIX: &str = "example";
+
+/// Writes a copy of `input` to `output` where every registrable domain not in
+/// `keep` is replaced by a hash keyed with `key`.
+///
+/// Domain ids are untouched so relationships, counters and flags survive as is.
+/// Tables outside of ITP (e.g. Private Click Measurement) are emptied since they
+/// may carry tokens or application identifiers.
+pub fn anonymize(input: &Path, output: &Path, key: &str, keep: &[String]) -> SQLiteResult<usize> {
+    let output_path = output
+        .to_str()
+        .ok_or_else(|| Error::InvalidPath(output.to_owned()))?;
+    let source = Connection::open_with_flags(input, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    source.execute("VACUUM INTO ?", params![output_path])?;
+
+    let mut connection = Connection::open(output)?;
+    connection.execute_batch("PRAGMA journal_mode = DELETE; PRAGMA secure_delete = ON;")?;
+
+    let keep: HashSet<&str> = keep.iter().map(String::as_str).collect();
+    let domains: Vec<(i64, String)> = {
+        let mut stmt = connection.prepare(OBSERVED_DOMAINS)?;
+        let rows = stmt.query_map(NO_PARAMS, |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<SQLiteResult<_>>()?
+    };
+
+    let transaction = connection.transaction()?;
+    let mut renamed = 0;
+
+    for (id, name) in domains.iter() {
+        if keep.contains(name.as_str()) {
+            continue;
+        }
+
+        transaction.execute(RENAME_DOMAIN, params![hash_domain(key, name), id])?;
+        renamed += 1;
+    }
+
+    let tables: Vec<String> = {
+        let mut stmt = transaction.prepare(USER_TABLES)?;
+        let rows = stmt.query_map(NO_PARAMS, |row| row.get(0))?;
+        rows.collect::<SQLiteResult<_>>()?
+    };
+
+    for table in tables.iter().filter(|t| !ITP_TABLES.contains(&t.as_str())) {
+        transaction.execute(&format!("DELETE FROM \"{}\"", table), NO_PARAMS)?;
+    }
+
+    transaction.commit()?;
+
+    // Rewrite the file so freed pages can't leak the original names.
+    connection.execute_batch("VACUUM")?;
+
+    Ok(renamed)
+}
+
+/// Stable replacement for `domain`, the same key always yields the same name.
+pub fn hash_domain(key: &str, domain: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(key.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(domain.as_bytes());
+
+    let digest = mac.finalize().into_bytes();
+    let hex: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+
+    format!("{}.{}", hex, ANONYMIZED_SUFFIX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    use crate::database::{Database, TemporaryDatabase};
+    use crate::fixture;
+    use crate::scenario::Scenario;
+    use crate::stats_source::{Relationship, StatsSource};
+    use crate::Config;
+
+    const KEY: &str = "secret";
+
+    /// A scenario database exported to a second temporary database.
+    fn export(name: &str, keep: &[String]) -> (TemporaryDatabase, TemporaryDatabase) {
+        let scenario: Scenario = toml::from_str(
+            r#"
+            [[domain]]
+            name = "tracker.example"
+            prevalent = true
+            iframed_under = ["news.example", "shop.example"]
+            subresource_under = ["blog.example"]
+            redirected_from = ["news.example"]
+            storage_access_under = ["shop.example"]
+
+            [[domain]]
+            name = "cdn.example"
+            subresource_under = ["news.example"]
+            "#,
+        )
+        .unwrap();
+        let input = TemporaryDatabase::new(fixture::temporary_path(&format!("{}.db", name)));
+        let output =
+            TemporaryDatabase::new(fixture::temporary_path(&format!("{}-shared.db", name)));
+        scenario.generate(input.path()).unwrap();
+
+        anonymize(input.path(), output.path(), KEY, keep).unwrap();
+        (input, output)
+    }
+
+    fn connect(path: &Path) -> Database {
+        Database::connect(Config {
+            path: Some(path.to_owned()),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn relationships(db: &Database) -> BTreeSet<(String, String, String)> {
+        db.relationships()
+            .into_iter()
+            .map(|Relationship { relation, from, to }| (format!("{:?}", relation), from, to))
+            .collect()
+    }
+
+    /// Every value of every table, as text.
+    fn values(path: &Path) -> Vec<String> {
+        let connection = Connection::open(path).unwrap();
+        let tables: Vec<String> = {
+            let mut stmt = connection.prepare(USER_TABLES).unwrap();
+            let rows = stmt.query_map(NO_PARAMS, |row| row.get(0)).unwrap();
+            rows.collect::<SQLiteResult<_>>().unwrap()
+        };
+        let mut values = Vec::new();
+
+        for table in tables {
+            let mut stmt = connection
+                .prepare(&format!("SELECT * FROM \"{}\"", table))
+                .unwrap();
+            let columns = stmt.column_count();
+            let mut rows = stmt.query(NO_PARAMS).unwrap();
+
+            while let Some(row) = rows.next().unwrap() {
+                for column in 0..columns {
+                    if let Ok(Some(value)) = row.get::<_, Option<String>>(column) {
+                        values.push(value);
+                    }
+                }
+            }
+        }
+
+        values
+    }
+
+    #[test]
+    fn replaces_every_registrable_domain() {
+        let (input, output) = export("anonymize", &[]);
+        let original = connect(input.path());
+        let names: Vec<String> = original
+            .get_domains()
+            .unwrap()
+            .into_iter()
+            .map(|domain| domain.name)
+            .collect();
+        let values = values(output.path());
+
+        assert!(!values.is_empty());
+        for name in names.iter() {
+            assert!(
+                values.iter().all(|value| !value.contains(name.as_str())),
+                "{} survived the export",
+                name
+            );
+        }
+
+        // Nor in pages freed along the way.
+        let bytes = String::from_utf8_lossy(&std::fs::read(output.path()).unwrap()).into_owned();
+        assert!(names.iter().all(|name| !bytes.contains(name.as_str())));
+
+        let shared = connect(output.path());
+        assert_eq!(shared.get_domains().unwrap().len(), names.len());
+        let tracker = shared.find(&hash_domain(KEY, "tracker.example")).unwrap();
+        assert!(shared.get_info(&tracker).unwrap().prevalent);
+    }
+
+    #[test]
+    fn keeps_relationships_between_renamed_domains() {
+        let keep = vec!["news.example".to_owned()];
+        let (input, output) = export("anonymize-relationships", &keep);
+        let rename = |name: String| match keep.contains(&name) {
+            true => name,
+            false => hash_domain(KEY, &name),
+        };
+        let expected: BTreeSet<_> = relationships(&connect(input.path()))
+            .into_iter()
+            .map(|(relation, from, to)| (relation, rename(from), rename(to)))
+            .collect();
+
+        assert!(!expected.is_empty());
+        assert_eq!(relationships(&connect(output.path())), expected);
+        assert!(connect(output.path()).find("news.example").is_some());
+    }
+
+    #[test]
+    fn hashes_domains_the_same_way_for_a_key() {
+        let hashed = hash_domain(KEY, "tracker.example");
+
+        assert_eq!(hashed, hash_domain(KEY, "tracker.example"));
+        assert_ne!(hashed, hash_domain("other", "tracker.example"));
+        assert!(hashed.ends_with(".example") && !hashed.contains("tracker"));
+    }
+}
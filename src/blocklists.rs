@@ -0,0 +1,301 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::database::Domain;
+
+/// A blocklist as configured under `[[blocklist]]`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct BlocklistConfig {
+    pub path: PathBuf,
+    /// Defaults to the file name.
+    pub name: Option<String>,
+    /// Category of every domain listed, Disconnect lists name their own.
+    pub category: Option<String>,
+}
+
+impl BlocklistConfig {
+    pub fn new(path: PathBuf) -> Self {
+        BlocklistConfig {
+            path,
+            name: None,
+            category: None,
+        }
+    }
+}
+
+/// A blocklist matching a domain.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tag {
+    pub list: String,
+    pub category: Option<String>,
+}
+
+impl Tag {
+    /// The category when known, the list name otherwise.
+    pub fn short(&self) -> &str {
+        self.category.as_deref().unwrap_or(&self.list)
+    }
+}
+
+/// Domains listed by a blocklist, along with their categories: Disconnect
+/// lists a domain under every category its services belong to.
+#[derive(Debug)]
+pub struct Blocklist {
+    name: String,
+    domains: HashMap<String, BTreeSet<String>>,
+}
+
+impl Blocklist {
+    /// Reads a hosts file, AdBlock domain rules or Disconnect's
+    /// `services.json`, telling them apart from their content.
+    pub fn from_config(config: &BlocklistConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(&config.path)?;
+        let name = config
+            .name
+            .clone()
+            .unwrap_or_else(|| file_name(&config.path));
+
+        let domains = match content.trim_start().starts_with('{') {
+            true => parse_disconnect(&serde_json::from_str(&content)?),
+            false => parse_rules(&content)
+                .into_iter()
+                .map(|domain| (domain, None))
+                .collect(),
+        };
+
+        let mut categories: HashMap<String, BTreeSet<String>> = HashMap::new();
+
+        for (domain, category) in domains {
+            let listed = categories.entry(domain).or_default();
+
+            if let Some(category) = category.or_else(|| config.category.clone()) {
+                listed.insert(category.to_lowercase());
+            }
+        }
+
+        Ok(Blocklist {
+            name,
+            domains: categories,
+        })
+    }
+
+    /// A tag per category `domain`, or the closest of its parent domains
+    /// being listed, is listed under. A single one without category when
+    /// the list doesn't name any.
+    pub fn tags(&self, domain: &str) -> Vec<Tag> {
+        let mut candidate = domain;
+
+        loop {
+            if let Some(categories) = self.domains.get(candidate) {
+                let tag = |category| Tag {
+                    list: self.name.clone(),
+                    category,
+                };
+
+                return match categories.is_empty() {
+                    true => vec![tag(None)],
+                    false => categories.iter().cloned().map(Some).map(tag).collect(),
+                };
+            }
+
+            candidate = match candidate.split_once('.') {
+                Some((_, parent)) => parent,
+                None => return Vec::new(),
+            };
+        }
+    }
+}
+
+/// Every configured blocklist, failing on the first one that can't be read.
+pub fn load(configs: &[BlocklistConfig]) -> Result<Vec<Blocklist>, String> {
+    configs
+        .iter()
+        .map(|list| {
+            Blocklist::from_config(list)
+                .map_err(|e| format!("Couldn't load {}: {}", list.path.display(), e))
+        })
+        .collect()
+}
+
+pub fn annotate(blocklists: &[Blocklist], domains: &mut [Domain]) {
+    for domain in domains.iter_mut() {
+        domain.tags = blocklists
+            .iter()
+            .flat_map(|list| list.tags(&domain.name))
+            .collect();
+    }
+}
+
+/// Separate the sites of a cosmetic rule from the elements it hides, as in
+/// `site.com##.ad`, `site.com#@#.ad` or `site.com#?#.ad:has(a)`.
+const COSMETIC_SEPARATORS: &[&str] = &["##", "#@#", "#?#"];
+
+fn file_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// Domains of a hosts file (`0.0.0.0 tracker.com`), of a plain list with
+/// one domain per line, or of AdBlock rules blocking whole domains
+/// (`||tracker.com^`). Comments, exceptions (`@@`), cosmetic rules hiding
+/// elements of a site (`site.com##.ad`) and rules narrower than a domain are
+/// skipped.
+fn parse_rules(content: &str) -> Vec<String> {
+    let mut domains = Vec::new();
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty()
+            || line.starts_with(['#', '!', '['])
+            || line.starts_with("@@")
+            || COSMETIC_SEPARATORS
+                .iter()
+                .any(|separator| line.contains(separator))
+        {
+            continue;
+        }
+
+        let domain = match line.strip_prefix("||") {
+            Some(rule) => {
+                let (rule, _options) = rule.split_once('$').unwrap_or((rule, ""));
+
+                match rule.strip_suffix('^') {
+                    Some(domain) => domain,
+                    None => continue,
+                }
+            }
+            None => {
+                let mut fields = line.split('#').next().unwrap_or("").split_whitespace();
+
+                match (fields.next(), fields.next()) {
+                    (Some(_address), Some(host)) => host,
+                    (Some(host), None) => host,
+                    _ => continue,
+                }
+            }
+        };
+
+        let domain = domain.to_lowercase();
+
+        if domain.contains('.')
+            && domain.parse::<IpAddr>().is_err()
+            && domain
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+        {
+            domains.push(domain);
+        }
+    }
+
+    domains
+}
+
+/// Disconnect's `services.json`, listing services by category:
+///
+/// ```json
+/// { "categories": { "Advertising": [ { "Ads Inc": { "https://ads.com/": ["ads.com"] } } ] } }
+/// ```
+fn parse_disconnect(file: &Value) -> Vec<(String, Option<String>)> {
+    let mut domains = Vec::new();
+    let categories = file.get("categories").and_then(Value::as_object);
+
+    for (category, services) in categories.into_iter().flatten() {
+        let services = services.as_array().into_iter().flatten();
+
+        for service in services.filter_map(Value::as_object) {
+            // Besides the service's sites, entries can hold flags such as "dnt".
+            let sites = service.values().filter_map(Value::as_object).flatten();
+
+            for listed in sites.filter_map(|(_, listed)| listed.as_array()).flatten() {
+                if let Some(domain) = listed.as_str() {
+                    domains.push((domain.to_lowercase(), Some(category.clone())));
+                }
+            }
+        }
+    }
+
+    domains
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocklist(file: &str, content: &str, category: Option<&str>) -> Blocklist {
+        let path = crate::fixture::temporary_path(file);
+        fs::write(&path, content).unwrap();
+
+        let blocklist = Blocklist::from_config(&BlocklistConfig {
+            category: category.map(str::to_owned),
+            ..BlocklistConfig::new(path.clone())
+        });
+        fs::remove_file(path).unwrap();
+        blocklist.unwrap()
+    }
+
+    fn categories(tags: Vec<Tag>) -> Vec<Option<String>> {
+        tags.into_iter().map(|tag| tag.category).collect()
+    }
+
+    #[test]
+    fn tags_every_category_of_a_domain() {
+        let services = r#"{ "categories": {
+            "Advertising": [ { "Ads Inc": { "https://ads.example/": ["ads.example"] } } ],
+            "Analytics": [ { "Ads Inc": { "https://ads.example/": ["ads.example"] } } ]
+        } }"#;
+        let disconnect = blocklist("services.json", services, None);
+
+        assert_eq!(
+            categories(disconnect.tags("cdn.ads.example")),
+            vec![Some("advertising".to_owned()), Some("analytics".to_owned())]
+        );
+        assert!(disconnect.tags("example").is_empty());
+    }
+
+    #[test]
+    fn skips_cosmetic_rules() {
+        assert!(parse_rules("example.com##.ad").is_empty());
+        assert!(parse_rules("example.com,news.example##div.banner").is_empty());
+        assert!(parse_rules("example.com#@#.ad").is_empty());
+        assert!(parse_rules("example.com#?#.ad:has(> a)").is_empty());
+    }
+
+    #[test]
+    fn skips_exceptions() {
+        assert!(parse_rules("@@||example.com^").is_empty());
+        assert!(parse_rules("@@||example.com^$document").is_empty());
+        assert_eq!(
+            parse_rules("@@||example.com^\n||tracker.example^"),
+            vec!["tracker.example"]
+        );
+    }
+
+    #[test]
+    fn parses_hosts_plain_lists_and_domain_rules() {
+        let rules = "0.0.0.0 tracker.example # Tracker\nads.example\n||cdn.example^$third-party\n||example.com/ads.js\n127.0.0.1 localhost";
+
+        assert_eq!(
+            parse_rules(rules),
+            vec!["tracker.example", "ads.example", "cdn.example"]
+        );
+    }
+
+    #[test]
+    fn tags_rules_with_the_configured_category() {
+        let rules = "# Trackers\n0.0.0.0 tracker.example\n||ads.example^\n";
+
+        assert_eq!(
+            categories(blocklist("hosts", rules, None).tags("tracker.example")),
+            vec![None]
+        );
+        assert_eq!(
+            categories(blocklist("rules", rules, Some("Ads")).tags("ads.example")),
+            vec![Some("ads".to_owned())]
+        );
+    }
+}
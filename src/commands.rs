@@ -0,0 +1,214 @@
+use std::path::PathBuf;
+
+use structopt::StructOpt;
+
+use crate::anonymize;
+use crate::blocklists;
+use crate::clock;
+use crate::discovery;
+use crate::domain_list::DomainList;
+use crate::entities::Entities;
+use crate::filter::Filter;
+use crate::lab::{self, Edit, Prevalence};
+use crate::scenario::Scenario;
+use crate::search::Search;
+use crate::sort::Sort;
+use crate::Config;
+
+#[derive(StructOpt, Debug)]
+pub enum Command {
+    /// Copies a database replacing every domain with a keyed hash, ready to be shared.
+    Anonymize {
+        /// Database to anonymize
+        input: PathBuf,
+        /// Where the anonymized copy is written, must not exist
+        output: PathBuf,
+        /// Secret used to hash domains, reuse it to get the same names across exports
+        #[structopt(short, long, env = "ITP_TLDR_KEY", hide_env_values = true)]
+        key: String,
+        /// A list of comma separated domains kept in clear
+        #[structopt(long, use_delimiter = true)]
+        keep: Vec<String>,
+    },
+    /// Generates a synthetic database out of a TOML scenario.
+    Scenario {
+        /// TOML file describing the domains and their relationships
+        scenario: PathBuf,
+        /// Where the database is written, must not exist
+        output: PathBuf,
+    },
+    /// Prints the observed domains with their classification and interactions.
+    List {
+        /// Only prints the domains matching the expression, such as `prevalent && iframes > 5`
+        #[structopt(long = "where")]
+        filter: Option<String>,
+    },
+    /// Edits ITP's state on a copy of the database and swaps it in, Safari must be closed.
+    Lab(LabCommand),
+}
+
+#[derive(StructOpt, Debug)]
+pub enum LabCommand {
+    /// Records a user interaction with a domain
+    Interaction {
+        domain: String,
+        /// Time of the interaction in seconds since the Unix epoch, defaults to now
+        #[structopt(long)]
+        at: Option<f64>,
+        /// Forgets any previous interaction instead
+        #[structopt(long, conflicts_with = "at")]
+        clear: bool,
+    },
+    /// Classifies a domain as prevalent
+    Prevalent {
+        domain: String,
+        /// Classifies it as very prevalent as well
+        #[structopt(long)]
+        very: bool,
+        /// Removes the classification instead
+        #[structopt(long, conflicts_with = "very")]
+        clear: bool,
+    },
+    /// Records a domain loaded as an iframe under a top frame
+    Subframe { subframe: String, top_frame: String },
+    /// Records a top frame redirect between two domains
+    Redirect { from: String, to: String },
+    /// Restores the database backed up before the first edit
+    Restore,
+}
+
+impl LabCommand {
+    fn edit(self) -> Option<Edit> {
+        match self {
+            LabCommand::Interaction { domain, at, clear } => {
+                let at = match clear {
+                    true => None,
+                    false => Some(at.unwrap_or_else(clock::now)),
+                };
+                Some(Edit::Interaction(domain, at))
+            }
+            LabCommand::Prevalent {
+                domain,
+                very,
+                clear,
+            } => {
+                let prevalence = match (clear, very) {
+                    (true, _) => Prevalence::None,
+                    (false, true) => Prevalence::VeryPrevalent,
+                    (false, false) => Prevalence::Prevalent,
+                };
+                Some(Edit::Prevalence(domain, prevalence))
+            }
+            LabCommand::Subframe {
+                subframe,
+                top_frame,
+            } => Some(Edit::Subframe {
+                subframe,
+                top_frame,
+            }),
+            LabCommand::Redirect { from, to } => Some(Edit::Redirect { from, to }),
+            LabCommand::Restore => None,
+        }
+    }
+}
+
+impl Command {
+    pub fn run(self, mut config: Config) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            Command::Anonymize {
+                input,
+                output,
+                key,
+                keep,
+            } => {
+                let renamed = anonymize::anonymize(&input, &output, &key, &keep)?;
+                println!("Anonymized {} domains into {}", renamed, output.display());
+            }
+            Command::Scenario { scenario, output } => {
+                if output.exists() {
+                    return Err(format!("{} already exists", output.display()).into());
+                }
+
+                let scenario = Scenario::from_file(&scenario)?;
+                scenario.generate(&output)?;
+                println!(
+                    "Generated {} domains into {}",
+                    scenario.domains.len(),
+                    output.display()
+                );
+            }
+            Command::List { filter } => {
+                let mut search = Search::default();
+                if let Some(source) = filter {
+                    search.filter = Some(
+                        Filter::parse(&source, &config.filters)
+                            .map_err(|e| format!("Invalid filter: {}", e))?,
+                    );
+                }
+                config.path = config.path.or_else(|| Some(discovery::default_path()));
+
+                let db = crate::connect(&config, &crate::labelled(&config))?;
+                let entities = match &config.entities {
+                    Some(path) => Some(Entities::from_file(path)?),
+                    None => None,
+                };
+                let blocklists = blocklists::load(&config.blocklists)?;
+                let list = DomainList::build(
+                    db.as_ref(),
+                    entities.as_ref(),
+                    &blocklists,
+                    false,
+                    &search,
+                    &Sort::default(),
+                );
+
+                println!(
+                    "{:<40} {:<10} {:<15} {:<11} {:>7} {:>8} {:>9}  TAGS",
+                    "DOMAIN",
+                    "PREVALENT",
+                    "VERY PREVALENT",
+                    "INTERACTED",
+                    "IFRAMES",
+                    "REQUESTS",
+                    "REDIRECTS"
+                );
+                for (info, interaction) in list
+                    .domains
+                    .iter()
+                    .zip(&list.interactions)
+                    .filter(|(domain, _)| domain.id != 0)
+                {
+                    let tags: Vec<_> = info.tags.iter().map(|tag| tag.short()).collect();
+
+                    println!(
+                        "{:<40} {:<10} {:<15} {:<11} {:>7} {:>8} {:>9}  {}",
+                        info.name,
+                        info.is_prevalent(),
+                        info.is_very_prevalent(),
+                        info.had_user_interaction(),
+                        interaction.iframes,
+                        interaction.requests,
+                        interaction.redirects,
+                        tags.join(", ")
+                    );
+                }
+            }
+            Command::Lab(command) => {
+                let path = config.path.unwrap_or_else(discovery::default_path);
+
+                match command.edit() {
+                    Some(edit) => {
+                        lab::apply(&path, &[edit])?;
+                        println!("Updated {}", path.display());
+                    }
+                    None => {
+                        lab::restore(&path)?;
+                        println!("Restored {}", path.display());
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
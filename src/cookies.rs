@@ -0,0 +1,401 @@
+use std::convert::TryInto;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::DateTime;
+
+const MAGIC: &[u8] = b"cook";
+const PAGE_HEADER: u32 = 0x0000_0100;
+/// Cookies are stored relative to Apple's reference date, 2001-01-01.
+const MAC_EPOCH: f64 = 978_307_200.0;
+
+const SECURE: u32 = 0x1;
+const HTTP_ONLY: u32 = 0x4;
+
+/// Where the cookie jar sits relative to the `Library` directory holding the
+/// `WebKit` directory the ITP database lives in.
+const COOKIES_PATH: &str = "Cookies/Cookies.binarycookies";
+
+/// ITP caps the lifetime of cookies set by scripts to seven days, or a day
+/// when the page was navigated to from a prevalent domain with link decoration.
+const SEVEN_DAYS: f64 = 7.0 * 24.0 * 3600.0;
+const ONE_DAY: f64 = 24.0 * 3600.0;
+/// Capped expiries are computed when the cookie is created, give or take.
+const CAP_TOLERANCE: f64 = 60.0;
+
+#[derive(Debug)]
+pub enum CookiesError {
+    InvalidFormat(&'static str),
+    Io(io::Error),
+}
+
+impl fmt::Display for CookiesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CookiesError::InvalidFormat(reason) => write!(f, "invalid cookies file: {}", reason),
+            CookiesError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CookiesError {}
+
+impl From<io::Error> for CookiesError {
+    fn from(e: io::Error) -> Self {
+        CookiesError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Cookie {
+    pub domain: String,
+    pub name: String,
+    pub path: String,
+    /// Seconds since the Unix epoch.
+    pub expiry: f64,
+    /// Seconds since the Unix epoch.
+    pub creation: f64,
+    pub secure: bool,
+    pub http_only: bool,
+}
+
+impl Cookie {
+    /// Whether the cookie was set for `domain` or one of its subdomains.
+    pub fn belongs_to(&self, domain: &str) -> bool {
+        let host = self.domain.trim_start_matches('.');
+        host == domain || host.ends_with(&format!(".{}", domain))
+    }
+
+    /// The ITP cap its lifetime matches, if any. Sites pick such lifetimes
+    /// too, so they are only put down to ITP on domains it restricts: the
+    /// ones classified as prevalent without user interaction.
+    pub fn cap(&self, prevalent: bool, had_user_interaction: bool) -> Option<&str> {
+        if !prevalent || had_user_interaction {
+            return None;
+        }
+
+        let lifetime = self.expiry - self.creation;
+
+        if (lifetime - ONE_DAY).abs() < CAP_TOLERANCE {
+            Some("24 hours")
+        } else if (lifetime - SEVEN_DAYS).abs() < CAP_TOLERANCE {
+            Some("7 days")
+        } else {
+            None
+        }
+    }
+
+    pub fn flags(&self) -> String {
+        let mut flags = Vec::new();
+
+        if self.secure {
+            flags.push("Secure");
+        }
+        if self.http_only {
+            flags.push("HttpOnly");
+        }
+
+        flags.join(", ")
+    }
+}
+
+/// Cookie jar of the Safari or WebKit app the ITP database at `database`
+/// belongs to, when there is one. Only `Library/WebKit` layouts are looked
+/// at, other browsers keeping their cookies elsewhere.
+pub fn locate(database: &Path) -> Option<PathBuf> {
+    database
+        .ancestors()
+        .find(|dir| {
+            dir.file_name().is_some_and(|name| name == "WebKit")
+                && dir
+                    .parent()
+                    .and_then(Path::file_name)
+                    .is_some_and(|name| name == "Library")
+        })
+        .and_then(Path::parent)
+        .map(|library| library.join(COOKIES_PATH))
+        .filter(|path| path.is_file())
+}
+
+pub fn read(path: &Path) -> Result<Vec<Cookie>, CookiesError> {
+    parse(&fs::read(path)?)
+}
+
+/// Parses the `Cookies.binarycookies` format: a big endian list of page sizes
+/// followed by pages of little endian cookie records.
+pub fn parse(bytes: &[u8]) -> Result<Vec<Cookie>, CookiesError> {
+    if bytes.get(..4) != Some(MAGIC) {
+        return Err(CookiesError::InvalidFormat("missing magic"));
+    }
+
+    let pages = be_u32(bytes, 4)? as usize;
+    let mut offset = 8 + pages * 4;
+    let mut cookies = Vec::new();
+
+    for index in 0..pages {
+        let size = be_u32(bytes, 8 + index * 4)? as usize;
+        let page = bytes
+            .get(offset..offset + size)
+            .ok_or(CookiesError::InvalidFormat("truncated page"))?;
+
+        cookies.extend(parse_page(page)?);
+        offset += size;
+    }
+
+    Ok(cookies)
+}
+
+fn parse_page(page: &[u8]) -> Result<Vec<Cookie>, CookiesError> {
+    if be_u32(page, 0)? != PAGE_HEADER {
+        return Err(CookiesError::InvalidFormat("invalid page header"));
+    }
+
+    let amount = le_u32(page, 4)? as usize;
+
+    (0..amount)
+        .map(|index| {
+            let start = le_u32(page, 8 + index * 4)? as usize;
+            let size = le_u32(page, start)? as usize;
+            let record = page
+                .get(start..start + size)
+                .ok_or(CookiesError::InvalidFormat("truncated cookie"))?;
+
+            parse_cookie(record)
+        })
+        .collect()
+}
+
+fn parse_cookie(record: &[u8]) -> Result<Cookie, CookiesError> {
+    let flags = le_u32(record, 8)?;
+
+    Ok(Cookie {
+        domain: string(record, le_u32(record, 16)? as usize)?,
+        name: string(record, le_u32(record, 20)? as usize)?,
+        path: string(record, le_u32(record, 24)? as usize)?,
+        expiry: le_f64(record, 40)? + MAC_EPOCH,
+        creation: le_f64(record, 48)? + MAC_EPOCH,
+        secure: flags & SECURE != 0,
+        http_only: flags & HTTP_ONLY != 0,
+    })
+}
+
+/// Formats seconds since the Unix epoch as a UTC date.
+pub fn format_time(time: f64) -> String {
+    DateTime::from_timestamp(time as i64, 0)
+        .map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+fn bytes_at<const N: usize>(bytes: &[u8], offset: usize) -> Result<[u8; N], CookiesError> {
+    bytes
+        .get(offset..offset + N)
+        .and_then(|slice| slice.try_into().ok())
+        .ok_or(CookiesError::InvalidFormat("unexpected end of data"))
+}
+
+fn be_u32(bytes: &[u8], offset: usize) -> Result<u32, CookiesError> {
+    bytes_at(bytes, offset).map(u32::from_be_bytes)
+}
+
+fn le_u32(bytes: &[u8], offset: usize) -> Result<u32, CookiesError> {
+    bytes_at(bytes, offset).map(u32::from_le_bytes)
+}
+
+fn le_f64(bytes: &[u8], offset: usize) -> Result<f64, CookiesError> {
+    bytes_at(bytes, offset).map(f64::from_le_bytes)
+}
+
+/// NUL terminated string starting at `offset`.
+fn string(record: &[u8], offset: usize) -> Result<String, CookiesError> {
+    let rest = record
+        .get(offset..)
+        .ok_or(CookiesError::InvalidFormat("string out of bounds"))?;
+    let end = rest.iter().position(|b| *b == 0).unwrap_or(rest.len());
+
+    Ok(String::from_utf8_lossy(&rest[..end]).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Jan 1st 2021, in seconds since the Unix epoch.
+    const CREATION: f64 = 1_609_459_200.0;
+
+    /// A cookie record, strings following the fixed size fields.
+    fn record(domain: &str, name: &str, expiry: f64, flags: u32) -> Vec<u8> {
+        let strings = [domain, name, "/", "value"];
+        let mut offsets = Vec::new();
+        let mut tail = Vec::new();
+
+        for string in strings.iter() {
+            offsets.push(56 + tail.len() as u32);
+            tail.extend_from_slice(string.as_bytes());
+            tail.push(0);
+        }
+
+        let mut record = Vec::new();
+        record.extend_from_slice(&(56 + tail.len() as u32).to_le_bytes());
+        record.extend_from_slice(&0u32.to_le_bytes());
+        record.extend_from_slice(&flags.to_le_bytes());
+        record.extend_from_slice(&0u32.to_le_bytes());
+        for offset in offsets {
+            record.extend_from_slice(&offset.to_le_bytes());
+        }
+        record.extend_from_slice(&[0; 8]);
+        record.extend_from_slice(&(expiry - MAC_EPOCH).to_le_bytes());
+        record.extend_from_slice(&(CREATION - MAC_EPOCH).to_le_bytes());
+        record.extend(tail);
+        record
+    }
+
+    /// A page holding `records`, each preceded by its offset in the header.
+    fn page(records: &[Vec<u8>]) -> Vec<u8> {
+        let mut page = PAGE_HEADER.to_be_bytes().to_vec();
+        page.extend_from_slice(&(records.len() as u32).to_le_bytes());
+
+        let mut offset = 8 + records.len() * 4 + 4;
+        for record in records {
+            page.extend_from_slice(&(offset as u32).to_le_bytes());
+            offset += record.len();
+        }
+        page.extend_from_slice(&[0; 4]);
+        for record in records {
+            page.extend_from_slice(record);
+        }
+        page
+    }
+
+    /// A cookie jar made of `pages`, followed by its checksum.
+    fn jar(pages: &[Vec<u8>]) -> Vec<u8> {
+        let mut jar = MAGIC.to_vec();
+        jar.extend_from_slice(&(pages.len() as u32).to_be_bytes());
+        for page in pages {
+            jar.extend_from_slice(&(page.len() as u32).to_be_bytes());
+        }
+        for page in pages {
+            jar.extend_from_slice(page);
+        }
+        jar.extend_from_slice(&[0; 8]);
+        jar
+    }
+
+    fn cookie(lifetime: f64) -> Cookie {
+        Cookie {
+            domain: ".tracker.example".to_owned(),
+            name: "id".to_owned(),
+            path: "/".to_owned(),
+            expiry: CREATION + lifetime,
+            creation: CREATION,
+            secure: false,
+            http_only: false,
+        }
+    }
+
+    #[test]
+    fn parses_a_single_page_jar() {
+        let bytes = jar(&[page(&[
+            record(".tracker.example", "id", CREATION + SEVEN_DAYS, SECURE),
+            record(
+                "news.example",
+                "session",
+                CREATION + ONE_DAY,
+                SECURE | HTTP_ONLY,
+            ),
+        ])]);
+        let cookies = parse(&bytes).unwrap();
+
+        assert_eq!(cookies.len(), 2);
+        assert_eq!(cookies[0].domain, ".tracker.example");
+        assert_eq!(cookies[0].name, "id");
+        assert_eq!(cookies[0].path, "/");
+        assert_eq!(cookies[0].creation, CREATION);
+        assert_eq!(cookies[0].expiry, CREATION + SEVEN_DAYS);
+        assert_eq!(cookies[0].flags(), "Secure");
+        assert_eq!(cookies[1].flags(), "Secure, HttpOnly");
+        assert!(cookies[0].belongs_to("tracker.example"));
+        assert!(!cookies[1].belongs_to("tracker.example"));
+    }
+
+    #[test]
+    fn rejects_a_bad_magic() {
+        let mut bytes = jar(&[page(&[])]);
+        bytes[..4].copy_from_slice(b"kooc");
+
+        assert!(matches!(
+            parse(&bytes),
+            Err(CookiesError::InvalidFormat("missing magic"))
+        ));
+        assert!(matches!(
+            parse(b"co"),
+            Err(CookiesError::InvalidFormat("missing magic"))
+        ));
+    }
+
+    #[test]
+    fn rejects_truncated_pages() {
+        // The page is shorter than its header.
+        let bytes = jar(&[vec![0, 0, 1]]);
+        assert!(matches!(
+            parse(&bytes),
+            Err(CookiesError::InvalidFormat("unexpected end of data"))
+        ));
+
+        let mut bytes = jar(&[page(&[])]);
+        bytes[8..12].copy_from_slice(&1000u32.to_be_bytes());
+        assert!(matches!(
+            parse(&bytes),
+            Err(CookiesError::InvalidFormat("truncated page"))
+        ));
+
+        let mut bytes = jar(&[page(&[])]);
+        bytes[12] = 0xff;
+        assert!(matches!(
+            parse(&bytes),
+            Err(CookiesError::InvalidFormat("invalid page header"))
+        ));
+    }
+
+    #[test]
+    fn rejects_cookies_running_past_their_page() {
+        let mut oversized = record(".tracker.example", "id", CREATION, 0);
+        oversized[..4].copy_from_slice(&1000u32.to_le_bytes());
+        assert!(matches!(
+            parse(&jar(&[page(&[oversized])])),
+            Err(CookiesError::InvalidFormat("truncated cookie"))
+        ));
+
+        // A single cookie, starting past the end of its page.
+        let mut outside = PAGE_HEADER.to_be_bytes().to_vec();
+        outside.extend_from_slice(&1u32.to_le_bytes());
+        outside.extend_from_slice(&500u32.to_le_bytes());
+        outside.extend_from_slice(&[0; 4]);
+        assert!(matches!(
+            parse(&jar(&[outside])),
+            Err(CookiesError::InvalidFormat("unexpected end of data"))
+        ));
+
+        let mut misplaced_name = record(".tracker.example", "id", CREATION, 0);
+        misplaced_name[20..24].copy_from_slice(&500u32.to_le_bytes());
+        assert!(matches!(
+            parse(&jar(&[page(&[misplaced_name])])),
+            Err(CookiesError::InvalidFormat("string out of bounds"))
+        ));
+    }
+
+    #[test]
+    fn caps_lifetimes_on_restricted_domains_only() {
+        // Prevalent without interaction, script-writable cookies.
+        assert_eq!(cookie(SEVEN_DAYS).cap(true, false), Some("7 days"));
+        assert_eq!(cookie(SEVEN_DAYS - 30.0).cap(true, false), Some("7 days"));
+        assert_eq!(cookie(ONE_DAY).cap(true, false), Some("24 hours"));
+        assert_eq!(cookie(30.0 * ONE_DAY).cap(true, false), None);
+
+        // Prevalent with interaction, or not prevalent at all.
+        assert_eq!(cookie(SEVEN_DAYS).cap(true, true), None);
+        assert_eq!(cookie(SEVEN_DAYS).cap(false, false), None);
+        assert_eq!(cookie(ONE_DAY).cap(false, true), None);
+    }
+}
@@ -1,28 +1,56 @@
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
 use rusqlite::{params, Connection, Result as SQLiteResult, Row, NO_PARAMS};
 
+use crate::blocklists::Tag;
+use crate::scope::Scope;
+use crate::stats_source::{Relation, Relationship, StatsSource};
 use crate::Config;
 extern crate dirs;
 
-const OBSERVED_DOMAINS: &'static str = "SELECT domainID, registrableDomain FROM ObservedDomains";
-const SCOPED_DOMAINS: &'static str =
+const OBSERVED_DOMAINS: &str = "SELECT domainID, registrableDomain FROM ObservedDomains";
+const SCOPED_DOMAINS: &str =
     "SELECT domainID, registrableDomain FROM ObservedDomains WHERE registrableDomain = ?";
-const DOMAINS_AMOUNT: &'static str = "SELECT count(*) FROM ObservedDomains";
-const DOMAIN_INFO: &'static str = "SELECT isPrevalent, isVeryPrevalent, timesAccessedAsFirstPartyDueToUserInteraction, timesAccessedAsFirstPartyDueToStorageAccessAPI FROM ObservedDomains WHERE domainID = ?";
-const IFRAME_DOMAIN_INFO: &'static str =
+const DOMAIN_INFO: &str = "SELECT isPrevalent, isVeryPrevalent, timesAccessedAsFirstPartyDueToUserInteraction, timesAccessedAsFirstPartyDueToStorageAccessAPI, hadUserInteraction, lastSeen, mostRecentUserInteractionTime FROM ObservedDomains WHERE domainID = ?";
+const DOMAINS_INFO: &str = "SELECT isPrevalent, isVeryPrevalent, timesAccessedAsFirstPartyDueToUserInteraction, timesAccessedAsFirstPartyDueToStorageAccessAPI, hadUserInteraction, lastSeen, mostRecentUserInteractionTime, domainID FROM ObservedDomains";
+const IFRAME_DOMAIN_INFO: &str =
     "SELECT count(*) FROM SubframeUnderTopFrameDomains WHERE subFrameDomainID = ?";
-const SUBRESOURCE_DOMAIN_INFO: &'static str =
+const SUBRESOURCE_DOMAIN_INFO: &str =
     "SELECT count(*) FROM SubresourceUnderTopFrameDomains WHERE subresourceDomainID = ?";
-const TOPFRAME_DOMAIN_REDIRECT: &'static str =
+const TOPFRAME_DOMAIN_REDIRECT: &str =
     "SELECT count(*) FROM TopFrameUniqueRedirectsTo WHERE toDomainID = ?";
+const IFRAME_COUNTS: &str =
+    "SELECT subFrameDomainID, count(*) FROM SubframeUnderTopFrameDomains GROUP BY subFrameDomainID";
+const SUBRESOURCE_COUNTS: &str = "SELECT subresourceDomainID, count(*) FROM SubresourceUnderTopFrameDomains GROUP BY subresourceDomainID";
+const REDIRECT_COUNTS: &str =
+    "SELECT toDomainID, count(*) FROM TopFrameUniqueRedirectsTo GROUP BY toDomainID";
+const IFRAME_RELATIONSHIPS: &str = "SELECT f.registrableDomain, t.registrableDomain FROM SubframeUnderTopFrameDomains r JOIN ObservedDomains f ON f.domainID = r.subFrameDomainID JOIN ObservedDomains t ON t.domainID = r.topFrameDomainID";
+const SUBRESOURCE_RELATIONSHIPS: &str = "SELECT f.registrableDomain, t.registrableDomain FROM SubresourceUnderTopFrameDomains r JOIN ObservedDomains f ON f.domainID = r.subresourceDomainID JOIN ObservedDomains t ON t.domainID = r.topFrameDomainID";
+const REDIRECT_RELATIONSHIPS: &str = "SELECT f.registrableDomain, t.registrableDomain FROM TopFrameUniqueRedirectsTo r JOIN ObservedDomains f ON f.domainID = r.sourceDomainID JOIN ObservedDomains t ON t.domainID = r.toDomainID";
+
+/// Whether a domain, once its details are fetched, holds a classification.
+pub type Check = fn(&Domain) -> bool;
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct Domain {
     pub id: i64,
     pub name: String,
-    prevalent: bool,
-    very_prevalent: bool,
+    pub prevalent: bool,
+    pub very_prevalent: bool,
     pub first_party_interaction: i32,
     pub first_party_store_access: i32,
+    pub had_user_interaction: bool,
+    /// Organization owning the domain, when an entity list is loaded.
+    pub entity: Option<String>,
+    /// Blocklists listing the domain, when blocklists are configured.
+    pub tags: Vec<Tag>,
+    /// Seconds since the Unix epoch.
+    pub last_seen: Option<f64>,
+    pub last_interaction: Option<f64>,
 }
 
 impl Domain {
@@ -39,18 +67,68 @@ impl Domain {
             false => "No",
         }
     }
+
+    pub fn had_user_interaction(&self) -> &str {
+        match self.had_user_interaction {
+            true => "Yes",
+            false => "No",
+        }
+    }
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct DomainInteraction {
     pub iframes: i32,
     pub requests: i32,
     pub redirects: i32,
 }
 
+/// Removes a database along with its journal files.
+pub fn remove(path: &Path) {
+    for suffix in ["", "-wal", "-shm"].iter() {
+        let _ = fs::remove_file(journal(path, suffix));
+    }
+}
+
+/// When the database was last written to, its write-ahead log included.
+pub fn modified(path: &Path) -> Option<SystemTime> {
+    ["", "-wal"]
+        .iter()
+        .filter_map(|suffix| fs::metadata(journal(path, suffix)).ok())
+        .filter_map(|metadata| metadata.modified().ok())
+        .max()
+}
+
+/// `path` followed by `suffix`, as SQLite names its journals.
+pub(crate) fn journal(path: &Path, suffix: &str) -> PathBuf {
+    let mut file = OsString::from(path.as_os_str());
+    file.push(suffix);
+    PathBuf::from(file)
+}
+
+/// A database created for this session only, removed once dropped, whether
+/// the program quits or fails.
+pub struct TemporaryDatabase(PathBuf);
+
+impl TemporaryDatabase {
+    pub fn new(path: PathBuf) -> Self {
+        TemporaryDatabase(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TemporaryDatabase {
+    fn drop(&mut self) {
+        remove(&self.0);
+    }
+}
+
 pub struct Database {
     connection: Connection,
-    scope: Option<Vec<String>>,
+    scope: Option<Scope>,
 }
 
 impl Database {
@@ -58,11 +136,69 @@ impl Database {
         let connection = Connection::open(config.path.unwrap())?;
         Ok(Database {
             connection,
-            scope: config.domains,
+            scope: config.scope,
         })
     }
 
-    pub fn get_domains(&self) -> SQLiteResult<Vec<Domain>> {
+    fn iframed_count(&self, domain: &Domain) -> i32 {
+        self.connection
+            .query_row(IFRAME_DOMAIN_INFO, params![domain.id], |row| {
+                Ok(row.get(0).unwrap_or(0))
+            })
+            .unwrap_or(0)
+    }
+
+    fn requests_count(&self, domain: &Domain) -> i32 {
+        self.connection
+            .query_row(SUBRESOURCE_DOMAIN_INFO, params![domain.id], |row| {
+                Ok(row.get(0).unwrap_or(0))
+            })
+            .unwrap_or(0)
+    }
+
+    fn redirects_count(&self, domain: &Domain) -> i32 {
+        self.connection
+            .query_row(TOPFRAME_DOMAIN_REDIRECT, params![domain.id], |row| {
+                Ok(row.get(0).unwrap_or(0))
+            })
+            .unwrap_or(0)
+    }
+
+    /// Counts by domain id out of a `GROUP BY` query, empty when it fails.
+    fn counts(&self, query: &str) -> HashMap<i64, i32> {
+        let mut stmt = match self.connection.prepare(query) {
+            Ok(stmt) => stmt,
+            Err(_) => return HashMap::new(),
+        };
+        let rows = stmt.query_map(NO_PARAMS, |row| Ok((row.get(0)?, row.get(1)?)));
+
+        match rows {
+            Ok(rows) => rows.filter_map(|r| r.ok()).collect(),
+            Err(_) => HashMap::new(),
+        }
+    }
+}
+
+/// Details of `domain` out of a row of `DOMAIN_INFO` or `DOMAINS_INFO`.
+fn read_info(domain: &Domain, row: &Row) -> SQLiteResult<Domain> {
+    Ok(Domain {
+        id: domain.id,
+        name: domain.name.clone(),
+        prevalent: row.get(0)?,
+        very_prevalent: row.get(1)?,
+        first_party_interaction: row.get(2)?,
+        first_party_store_access: row.get(3)?,
+        had_user_interaction: row.get(4)?,
+        last_seen: Some(row.get(5)?),
+        // Zeroed once the interaction expires.
+        last_interaction: Some(row.get::<_, f64>(6)?).filter(|time| *time > 0.0),
+        entity: domain.entity.clone(),
+        tags: domain.tags.clone(),
+    })
+}
+
+impl StatsSource for Database {
+    fn get_domains(&self) -> SQLiteResult<Vec<Domain>> {
         let map_domains = |row: &Row| {
             Ok(Domain {
                 id: row.get(0)?,
@@ -71,63 +207,106 @@ impl Database {
             })
         };
 
+        let mut stmt = self.connection.prepare(OBSERVED_DOMAINS)?;
+        let domains = stmt
+            .query_map(NO_PARAMS, map_domains)?
+            .filter_map(|d| d.ok())
+            .collect();
+
         match &self.scope {
-            Some(scoped_domains) => {
-                let domains = scoped_domains
-                    .into_iter()
-                    .map(|domain| {
-                        self.connection
-                            .query_row(&SCOPED_DOMAINS, params![domain], map_domains)
-                            .unwrap_or(Domain {
-                                id: 0,
-                                name: domain.to_owned(),
-                                ..Default::default()
-                            })
-                    })
-                    .collect();
-
-                Ok(domains)
-            }
-            None => {
-                let mut stmt = self.connection.prepare(&OBSERVED_DOMAINS)?;
-                let domains = stmt
-                    .query_map(NO_PARAMS, map_domains)?
-                    .filter_map(|d| d.ok())
-                    .collect();
-                Ok(domains)
-            }
+            Some(scope) => Ok(scope.select(domains)),
+            None => Ok(domains),
         }
     }
 
-    pub fn get_info(&self, domain: &Domain) -> SQLiteResult<Domain> {
-        let info = self
-            .connection
-            .query_row(&DOMAIN_INFO, params![domain.id], |row| {
+    fn get_info(&self, domain: &Domain) -> SQLiteResult<Domain> {
+        self.connection
+            .query_row(DOMAIN_INFO, params![domain.id], |row| {
+                read_info(domain, row)
+            })
+    }
+
+    /// The observed domain named `name`, regardless of the configured scope.
+    fn find(&self, name: &str) -> Option<Domain> {
+        self.connection
+            .query_row(SCOPED_DOMAINS, params![name], |row| {
                 Ok(Domain {
-                    id: domain.id,
-                    name: domain.name.clone(),
-                    prevalent: row.get(0)?,
-                    very_prevalent: row.get(1)?,
-                    first_party_interaction: row.get(2)?,
-                    first_party_store_access: row.get(3)?,
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    ..Default::default()
                 })
             })
-            .expect("fetch domain info");
+            .ok()
+    }
+
+    fn relationships(&self) -> Vec<Relationship> {
+        let queries = [
+            (Relation::Iframe, IFRAME_RELATIONSHIPS),
+            (Relation::Subresource, SUBRESOURCE_RELATIONSHIPS),
+            (Relation::Redirect, REDIRECT_RELATIONSHIPS),
+        ];
+        let mut relationships = Vec::new();
+
+        for (relation, query) in queries.iter() {
+            let mut stmt = match self.connection.prepare(query) {
+                Ok(stmt) => stmt,
+                Err(_) => continue,
+            };
+            let rows = stmt.query_map(NO_PARAMS, |row| {
+                Ok(Relationship {
+                    relation: *relation,
+                    from: row.get(0)?,
+                    to: row.get(1)?,
+                })
+            });
 
-        Ok(info)
+            if let Ok(rows) = rows {
+                relationships.extend(rows.filter_map(|r| r.ok()));
+            }
+        }
+
+        relationships
     }
 
-    pub fn domains_len(&self) -> SQLiteResult<i32> {
-        match &self.scope {
-            Some(domains) => Ok(domains.len() as i32),
-            None => self
-                .connection
-                .query_row(&DOMAINS_AMOUNT, NO_PARAMS, |r| Ok(r.get(0)))
-                .expect("fetch domain amount"),
+    /// Four queries in all, rather than four per domain.
+    fn details(&self, domains: Vec<Domain>) -> Vec<(Domain, DomainInteraction)> {
+        let mut infos: HashMap<i64, Domain> = HashMap::new();
+
+        if let Ok(mut stmt) = self.connection.prepare(DOMAINS_INFO) {
+            let by_id: HashMap<i64, &Domain> =
+                domains.iter().map(|domain| (domain.id, domain)).collect();
+
+            if let Ok(mut rows) = stmt.query(NO_PARAMS) {
+                while let Ok(Some(row)) = rows.next() {
+                    let domain = row.get(7).ok().and_then(|id| by_id.get(&id));
+
+                    if let Some(info) = domain.and_then(|domain| read_info(domain, row).ok()) {
+                        infos.insert(info.id, info);
+                    }
+                }
+            }
         }
+
+        let iframes = self.counts(IFRAME_COUNTS);
+        let requests = self.counts(SUBRESOURCE_COUNTS);
+        let redirects = self.counts(REDIRECT_COUNTS);
+        let count = |counts: &HashMap<i64, i32>, id| counts.get(&id).copied().unwrap_or(0);
+
+        domains
+            .into_iter()
+            .map(|domain| {
+                let interaction = DomainInteraction {
+                    iframes: count(&iframes, domain.id),
+                    requests: count(&requests, domain.id),
+                    redirects: count(&redirects, domain.id),
+                };
+
+                (infos.remove(&domain.id).unwrap_or(domain), interaction)
+            })
+            .collect()
     }
 
-    pub fn domain_interaction(&self, domain: &Domain) -> DomainInteraction {
+    fn domain_interaction(&self, domain: &Domain) -> DomainInteraction {
         let iframe_count = self.iframed_count(domain);
         let requests_count = self.requests_count(domain);
         let redirects_count = self.redirects_count(domain);
@@ -138,28 +317,81 @@ impl Database {
             redirects: redirects_count,
         }
     }
+}
 
-    fn iframed_count(&self, domain: &Domain) -> i32 {
-        self.connection
-            .query_row(&IFRAME_DOMAIN_INFO, params![domain.id], |row| {
-                Ok(row.get(0).unwrap_or(0))
-            })
-            .unwrap_or(0)
-    }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture;
+    use crate::scenario::Scenario;
 
-    fn requests_count(&self, domain: &Domain) -> i32 {
-        self.connection
-            .query_row(&SUBRESOURCE_DOMAIN_INFO, params![domain.id], |row| {
-                Ok(row.get(0).unwrap_or(0))
-            })
-            .unwrap_or(0)
-    }
+    #[test]
+    fn details_every_domain_as_one_by_one() {
+        let scenario: Scenario = toml::from_str(
+            r#"
+            [[domain]]
+            name = "tracker.example"
+            prevalent = true
+            iframed_under = 3
+            subresource_under = ["news.example"]
+            redirected_from = ["news.example", "shop.example"]
 
-    fn redirects_count(&self, domain: &Domain) -> i32 {
-        self.connection
-            .query_row(&TOPFRAME_DOMAIN_REDIRECT, params![domain.id], |row| {
-                Ok(row.get(0).unwrap_or(0))
-            })
-            .unwrap_or(0)
+            [[domain]]
+            name = "news.example"
+            user_interaction = true
+            first_party_interactions = 2
+            "#,
+        )
+        .unwrap();
+        let path = TemporaryDatabase::new(fixture::temporary_path("details.db"));
+        scenario.generate(path.path()).unwrap();
+        let db = Database::connect(Config {
+            path: Some(path.path().to_owned()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let mut domains = db.get_domains().unwrap();
+        domains.push(Domain {
+            name: "unseen.example".to_owned(),
+            ..Default::default()
+        });
+        let details = db.details(domains.clone());
+
+        assert_eq!(details.len(), domains.len());
+        for (domain, (info, interaction)) in domains.iter().zip(details.iter()) {
+            assert_eq!(info.name, domain.name);
+
+            if domain.id == 0 {
+                assert!(!info.prevalent && info.last_seen.is_none());
+                continue;
+            }
+            let expected = db.get_info(domain).unwrap();
+            let expected_interaction = db.domain_interaction(domain);
+            assert_eq!(info.prevalent, expected.prevalent);
+            assert_eq!(info.had_user_interaction, expected.had_user_interaction);
+            assert_eq!(
+                info.first_party_interaction,
+                expected.first_party_interaction
+            );
+            assert_eq!(info.last_seen, expected.last_seen);
+            assert_eq!(interaction.iframes, expected_interaction.iframes);
+            assert_eq!(interaction.requests, expected_interaction.requests);
+            assert_eq!(interaction.redirects, expected_interaction.redirects);
+        }
+
+        let (tracker, interaction) = details
+            .iter()
+            .find(|(domain, _)| domain.name == "tracker.example")
+            .unwrap();
+        assert!(tracker.prevalent);
+        assert_eq!(
+            (
+                interaction.iframes,
+                interaction.requests,
+                interaction.redirects
+            ),
+            (3, 1, 2)
+        );
     }
 }
@@ -0,0 +1,333 @@
+use std::collections::{HashMap, HashSet};
+
+use rusqlite::Result as SQLiteResult;
+
+use crate::database::{Domain, DomainInteraction};
+use crate::discovery::Candidate;
+use crate::scope::Scope;
+use crate::stats_source::{self, Relationship, SourceInfo, StatsSource};
+use crate::Config;
+
+/// Several databases shown as one, such as different profiles, simulators,
+/// browsers or snapshots shared by teammates.
+///
+/// Domains are matched by name since ids are local to each database. The
+/// merged classification holds if any database holds it, counters add up.
+///
+/// A merged domain carries the id of the first database observing it, which
+/// means nothing across databases: it only tells observed domains apart from
+/// scoped ones observed nowhere (id 0). Details are looked up by name in each
+/// database through `observations`.
+pub struct DatabaseSet {
+    sources: Vec<(String, Box<dyn StatsSource>)>,
+}
+
+impl DatabaseSet {
+    pub fn connect(candidates: &[Candidate], scope: Option<Scope>) -> SQLiteResult<Self> {
+        let mut sources = Vec::new();
+
+        for (index, candidate) in candidates.iter().enumerate() {
+            let database = stats_source::connect(Config {
+                path: Some(candidate.path.clone()),
+                scope: scope.clone(),
+                ..Default::default()
+            })?;
+
+            // Snapshots often share a name, the position tells them apart.
+            let label = match candidates
+                .iter()
+                .filter(|c| c.label == candidate.label)
+                .count()
+            {
+                1 => candidate.label.clone(),
+                _ => format!("{} #{}", candidate.label, index + 1),
+            };
+            sources.push((label, database));
+        }
+
+        Ok(DatabaseSet { sources })
+    }
+
+    /// The domain as known by each database, along with its local id.
+    fn observations(&self, domain: &Domain) -> Vec<(&str, &dyn StatsSource, Option<Domain>)> {
+        self.sources
+            .iter()
+            .map(|(label, database)| {
+                (
+                    label.as_str(),
+                    database.as_ref(),
+                    database.find(&domain.name),
+                )
+            })
+            .collect()
+    }
+}
+
+impl StatsSource for DatabaseSet {
+    fn get_domains(&self) -> SQLiteResult<Vec<Domain>> {
+        let mut domains: Vec<Domain> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+
+        for (_, database) in self.sources.iter() {
+            for domain in database.get_domains()? {
+                match positions.get(&domain.name) {
+                    Some(&position) if domains[position].id == 0 => {
+                        domains[position].id = domain.id
+                    }
+                    Some(_) => {}
+                    None => {
+                        positions.insert(domain.name.clone(), domains.len());
+                        domains.push(domain);
+                    }
+                }
+            }
+        }
+
+        Ok(domains)
+    }
+
+    fn get_info(&self, domain: &Domain) -> SQLiteResult<Domain> {
+        let mut merged = Domain {
+            id: domain.id,
+            name: domain.name.clone(),
+            entity: domain.entity.clone(),
+            tags: domain.tags.clone(),
+            ..Default::default()
+        };
+
+        for source in self.breakdown(domain) {
+            if let Some(info) = source.info {
+                merge_info(&mut merged, &info);
+            }
+        }
+
+        Ok(merged)
+    }
+
+    fn domain_interaction(&self, domain: &Domain) -> DomainInteraction {
+        let mut merged = DomainInteraction::default();
+
+        for (_, database, local) in self.observations(domain) {
+            if let Some(local) = local {
+                merge_interaction(&mut merged, &database.domain_interaction(&local));
+            }
+        }
+
+        merged
+    }
+
+    /// Each database details its own domains at once, merged by name.
+    fn details(&self, domains: Vec<Domain>) -> Vec<(Domain, DomainInteraction)> {
+        let details: Vec<HashMap<String, (Domain, DomainInteraction)>> = self
+            .sources
+            .iter()
+            .map(|(_, database)| {
+                database
+                    .details(database.get_domains().unwrap_or_default())
+                    .into_iter()
+                    .filter(|(domain, _)| domain.id != 0)
+                    .map(|row| (row.0.name.clone(), row))
+                    .collect()
+            })
+            .collect();
+
+        domains
+            .into_iter()
+            .map(|domain| {
+                let observed: Vec<_> = details.iter().filter_map(|d| d.get(&domain.name)).collect();
+                let mut interaction = DomainInteraction::default();
+                let mut merged = Domain {
+                    id: domain.id,
+                    name: domain.name,
+                    entity: domain.entity,
+                    tags: domain.tags,
+                    ..Default::default()
+                };
+
+                for (info, local) in observed {
+                    merge_info(&mut merged, info);
+                    merge_interaction(&mut interaction, local);
+                }
+
+                (merged, interaction)
+            })
+            .collect()
+    }
+
+    fn relationships(&self) -> Vec<Relationship> {
+        let mut relationships: Vec<Relationship> = Vec::new();
+        let mut seen: HashSet<Relationship> = HashSet::new();
+
+        for (_, database) in self.sources.iter() {
+            for relationship in database.relationships() {
+                if seen.insert(relationship.clone()) {
+                    relationships.push(relationship);
+                }
+            }
+        }
+
+        relationships
+    }
+
+    fn breakdown(&self, domain: &Domain) -> Vec<SourceInfo> {
+        self.observations(domain)
+            .into_iter()
+            .map(|(label, database, local)| SourceInfo {
+                label: label.to_owned(),
+                info: local.and_then(|local| database.get_info(&local).ok()),
+            })
+            .collect()
+    }
+}
+
+/// Holds a classification if any database holds it, counters add up.
+fn merge_info(merged: &mut Domain, info: &Domain) {
+    merged.prevalent |= info.prevalent;
+    merged.very_prevalent |= info.very_prevalent;
+    merged.had_user_interaction |= info.had_user_interaction;
+    merged.first_party_interaction += info.first_party_interaction;
+    merged.first_party_store_access += info.first_party_store_access;
+    merged.last_seen = latest(merged.last_seen, info.last_seen);
+    merged.last_interaction = latest(merged.last_interaction, info.last_interaction);
+}
+
+fn merge_interaction(merged: &mut DomainInteraction, interaction: &DomainInteraction) {
+    merged.iframes += interaction.iframes;
+    merged.requests += interaction.requests;
+    merged.redirects += interaction.redirects;
+}
+
+fn latest(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+    use crate::stats_source::Relation;
+
+    fn domain(name: &str, prevalent: bool, first_party_interaction: i32, last_seen: f64) -> Domain {
+        Domain {
+            name: name.to_owned(),
+            prevalent,
+            first_party_interaction,
+            last_seen: Some(last_seen),
+            ..Default::default()
+        }
+    }
+
+    fn interaction(iframes: i32, requests: i32, redirects: i32) -> DomainInteraction {
+        DomainInteraction {
+            iframes,
+            requests,
+            redirects,
+        }
+    }
+
+    /// Two profiles seeing tracker.example, under different ids.
+    fn set() -> DatabaseSet {
+        let work = Fixture::new()
+            .with_domain(domain("news.example", false, 3, 10.0), interaction(0, 0, 0))
+            .with_domain(
+                domain("tracker.example", false, 0, 20.0),
+                interaction(2, 1, 0),
+            )
+            .with_relationship(Relation::Iframe, "tracker.example", "news.example");
+        let personal = Fixture::new()
+            .with_domain(
+                domain("tracker.example", true, 1, 15.0),
+                interaction(3, 0, 1),
+            )
+            .with_domain(domain("shop.example", false, 0, 5.0), interaction(0, 0, 0))
+            .with_relationship(Relation::Iframe, "tracker.example", "news.example")
+            .with_relationship(Relation::Redirect, "shop.example", "tracker.example");
+
+        DatabaseSet {
+            sources: vec![
+                ("Work".to_owned(), Box::new(work)),
+                ("Personal".to_owned(), Box::new(personal)),
+            ],
+        }
+    }
+
+    fn find(set: &DatabaseSet, name: &str) -> Domain {
+        set.get_domains()
+            .unwrap()
+            .into_iter()
+            .find(|domain| domain.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn merges_domains_by_name() {
+        let set = set();
+        let names: Vec<String> = set
+            .get_domains()
+            .unwrap()
+            .into_iter()
+            .map(|domain| domain.name)
+            .collect();
+
+        assert_eq!(
+            names,
+            vec!["news.example", "tracker.example", "shop.example"]
+        );
+
+        let tracker = set.get_info(&find(&set, "tracker.example")).unwrap();
+        assert!(tracker.prevalent);
+        assert_eq!(tracker.first_party_interaction, 1);
+        assert_eq!(tracker.last_seen, Some(20.0));
+
+        let interaction = set.domain_interaction(&tracker);
+        assert_eq!(
+            (
+                interaction.iframes,
+                interaction.requests,
+                interaction.redirects
+            ),
+            (5, 1, 1)
+        );
+        assert_eq!(set.relationships().len(), 2);
+    }
+
+    #[test]
+    fn details_every_domain_as_one_by_one() {
+        let set = set();
+        let domains = set.get_domains().unwrap();
+
+        for (domain, (info, interaction)) in domains.iter().zip(set.details(domains.clone())) {
+            let expected = set.get_info(domain).unwrap();
+            let expected_interaction = set.domain_interaction(domain);
+
+            assert_eq!(info.name, expected.name);
+            assert_eq!(info.prevalent, expected.prevalent);
+            assert_eq!(
+                info.first_party_interaction,
+                expected.first_party_interaction
+            );
+            assert_eq!(info.last_seen, expected.last_seen);
+            assert_eq!(interaction.iframes, expected_interaction.iframes);
+            assert_eq!(interaction.redirects, expected_interaction.redirects);
+        }
+    }
+
+    #[test]
+    fn breaks_domains_down_by_source() {
+        let set = set();
+        let breakdown = set.breakdown(&find(&set, "tracker.example"));
+
+        assert_eq!(breakdown.len(), 2);
+        assert_eq!(breakdown[0].label, "Work");
+        assert!(!breakdown[0].info.as_ref().unwrap().prevalent);
+        assert_eq!(breakdown[1].label, "Personal");
+        assert!(breakdown[1].info.as_ref().unwrap().prevalent);
+
+        let shop = set.breakdown(&find(&set, "shop.example"));
+        assert!(shop[0].info.is_none());
+        assert!(shop[1].info.is_some());
+    }
+}
@@ -0,0 +1,227 @@
+use std::path::PathBuf;
+use std::process;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, Result as SQLiteResult, NO_PARAMS};
+
+use crate::clock;
+use crate::database;
+use crate::scenario::{Observations, Scenario};
+
+/// Starting point of the demo, the same scenario shipped as an example.
+const DEMO_SCENARIO: &str = include_str!("../scenarios/tracker.toml");
+
+const SITES: &[&str] = &[
+    "news.example",
+    "shop.example",
+    "blog.example",
+    "mail.example",
+    "video.example",
+    "bank.example",
+    "social.example",
+    "search.example",
+];
+const THIRD_PARTIES: &[&str] = &[
+    "tracker.example",
+    "ads.example",
+    "analytics.example",
+    "pixel.example",
+    "cdn.example",
+    "fonts.example",
+];
+const BOUNCERS: &[&str] = &["click.example", "link.example", "out.example"];
+
+/// How long a user interaction lasts, ITP's 30 days squeezed into a minute
+/// so that the demo goes through every transition.
+const INTERACTION_LIFETIME: f64 = 60.0;
+const EXPIRE_INTERACTION: &str = "UPDATE ObservedDomains SET hadUserInteraction = 0 WHERE hadUserInteraction = 1 AND mostRecentUserInteractionTime < ?";
+/// Domains the user interacted with no longer have their data removed.
+const DEMOTE: &str = "UPDATE ObservedDomains SET isPrevalent = 0, isVeryPrevalent = 0 WHERE hadUserInteraction = 1 AND isPrevalent = 1";
+/// Rough stand-in for ITP's classifier: domains seen as a third party under
+/// enough sites without any user interaction become prevalent.
+const CLASSIFY: &str = "UPDATE ObservedDomains SET isPrevalent = 1, isVeryPrevalent = (
+    SELECT count(*) FROM SubframeUnderTopFrameDomains WHERE subFrameDomainID = domainID
+) + (
+    SELECT count(*) FROM SubresourceUnderTopFrameDomains WHERE subresourceDomainID = domainID
+) + (
+    SELECT count(*) FROM TopFrameUniqueRedirectsTo WHERE toDomainID = domainID
+) >= 10 WHERE hadUserInteraction = 0 AND (
+    SELECT count(*) FROM SubframeUnderTopFrameDomains WHERE subFrameDomainID = domainID
+) + (
+    SELECT count(*) FROM SubresourceUnderTopFrameDomains WHERE subresourceDomainID = domainID
+) + (
+    SELECT count(*) FROM TopFrameUniqueRedirectsTo WHERE toDomainID = domainID
+) >= 3";
+
+/// Creates a temporary database seeded with the demo scenario.
+pub fn prepare() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("itp_tldr-demo-{}.db", process::id()));
+    database::remove(&path);
+
+    let scenario: Scenario = toml::from_str(DEMO_SCENARIO)?;
+    scenario.generate(&path)?;
+
+    // Lets the UI read while the demo writes.
+    Connection::open(&path)?.execute_batch("PRAGMA journal_mode = WAL")?;
+
+    Ok(path)
+}
+
+/// Keeps inserting navigation events into the database at `path`.
+pub fn spawn(path: PathBuf, interval: Duration) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let connection = match Connection::open(&path) {
+            Ok(connection) => connection,
+            Err(_) => return,
+        };
+        let mut random = Random::seeded();
+
+        loop {
+            thread::sleep(interval);
+
+            if simulate(&connection, &mut random).is_err() {
+                return;
+            }
+        }
+    })
+}
+
+fn simulate(connection: &Connection, random: &mut Random) -> SQLiteResult<()> {
+    let mut observations = Observations::new(connection);
+    let site = random.pick(SITES);
+
+    match random.below(5) {
+        0 => {
+            observations.top_frame(site)?;
+        }
+        1 => observations.subframe(random.pick(THIRD_PARTIES), site)?,
+        2 => observations.subresource(random.pick(THIRD_PARTIES), site)?,
+        3 => {
+            let bouncer = random.pick(BOUNCERS);
+            observations.redirect(site, bouncer)?;
+            observations.redirect(bouncer, random.pick(SITES))?;
+        }
+        _ => observations.interaction(site)?,
+    }
+
+    classify(connection, clock::now())
+}
+
+/// Expires interactions older than the lifetime at `now`, then demotes the
+/// domains the user interacted with and promotes the others.
+fn classify(connection: &Connection, now: f64) -> SQLiteResult<()> {
+    connection.execute(EXPIRE_INTERACTION, params![now - INTERACTION_LIFETIME])?;
+    connection.execute(DEMOTE, NO_PARAMS)?;
+    connection.execute(CLASSIFY, NO_PARAMS)?;
+
+    Ok(())
+}
+
+/// xorshift64, plenty for picking the next fake navigation.
+struct Random(u64);
+
+impl Random {
+    fn seeded() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos() as u64)
+            .unwrap_or(0);
+
+        Random(nanos | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::TemporaryDatabase;
+    use crate::fixture::temporary_path;
+
+    fn demo(name: &str) -> TemporaryDatabase {
+        let database = TemporaryDatabase::new(temporary_path(name));
+        let scenario: Scenario = toml::from_str(DEMO_SCENARIO).unwrap();
+        scenario.generate(database.path()).unwrap();
+        database
+    }
+
+    /// Whether the domain named `name` had user interaction and is prevalent.
+    fn state(connection: &Connection, name: &str) -> (bool, bool) {
+        connection
+            .query_row(
+                "SELECT hadUserInteraction, isPrevalent FROM ObservedDomains WHERE registrableDomain = ?",
+                params![name],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap()
+    }
+
+    fn events(connection: &Connection) -> i64 {
+        connection
+            .query_row(
+                "SELECT (SELECT count(*) FROM ObservedDomains) + (SELECT count(*) FROM SubframeUnderTopFrameDomains) + (SELECT count(*) FROM SubresourceUnderTopFrameDomains) + (SELECT count(*) FROM TopFrameUniqueRedirectsTo) + (SELECT sum(timesAccessedAsFirstPartyDueToUserInteraction) FROM ObservedDomains)",
+                NO_PARAMS,
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn ticks_add_traffic() {
+        let database = demo("demo-ticks.db");
+        let connection = Connection::open(database.path()).unwrap();
+        let before = events(&connection);
+
+        let mut random = Random(1);
+        for _ in 0..50 {
+            simulate(&connection, &mut random).unwrap();
+        }
+
+        assert!(events(&connection) > before);
+        // Seen under a dozen sites without interaction.
+        assert_eq!(state(&connection, "tracker.example"), (false, true));
+    }
+
+    #[test]
+    fn classifies_promotes_demotes_and_expires() {
+        let database = demo("demo-classify.db");
+        let connection = Connection::open(database.path()).unwrap();
+        let mut observations = Observations::new(&connection);
+        for site in &SITES[..3] {
+            observations.subframe("pixel.example", site).unwrap();
+        }
+        let now = clock::now();
+
+        classify(&connection, now).unwrap();
+        assert_eq!(state(&connection, "pixel.example"), (false, true));
+
+        // Interacting with a prevalent domain demotes it.
+        Observations::new(&connection)
+            .interaction("pixel.example")
+            .unwrap();
+        classify(&connection, now).unwrap();
+        assert_eq!(state(&connection, "pixel.example"), (true, false));
+
+        // Until the interaction expires.
+        classify(&connection, now + INTERACTION_LIFETIME + 1.0).unwrap();
+        assert_eq!(state(&connection, "pixel.example"), (false, true));
+
+        // The seeded interaction from 2021 is long gone.
+        assert!(!state(&connection, "login.example").0);
+    }
+}
@@ -0,0 +1,228 @@
+use rusqlite::{params, Connection, Result as SQLiteResult, Row, NO_PARAMS};
+
+use crate::database::{Domain, DomainInteraction};
+use crate::scope::Scope;
+use crate::stats_source::StatsSource;
+use crate::Config;
+
+const SITES: &str = "SELECT rowid, site FROM bounces";
+const SCOPED_SITES: &str = "SELECT rowid, site FROM bounces WHERE site = ?";
+const SITE_INFO: &str = "SELECT last_user_interaction_time, last_stateful_bounce_time IS NOT NULL, max(coalesce(last_site_storage_time, 0), coalesce(last_user_interaction_time, 0), coalesce(last_stateful_bounce_time, 0), coalesce(last_bounce_time, 0)) FROM bounces WHERE rowid = ?";
+/// Chromium times count microseconds since 1601.
+const WINDOWS_EPOCH_OFFSET: f64 = 11_644_473_600.0;
+
+/// Chromium's bounce tracking mitigation (DIPS) database, found in every
+/// profile directory as `DIPS`.
+///
+/// Chromium keeps timestamps rather than relationships, so they are mapped
+/// onto ITP's model: a site that bounced statefully without any user
+/// interaction is about to have its state cleared and is shown as prevalent,
+/// its bounce as a redirect without user interaction. Chromium doesn't count
+/// interactions, only whether there was one.
+pub struct Dips {
+    connection: Connection,
+    scope: Option<Scope>,
+}
+
+impl Dips {
+    /// Table telling a DIPS database apart.
+    pub const TABLE: &'static str = "bounces";
+
+    pub fn connect(config: Config) -> SQLiteResult<Self> {
+        let connection = Connection::open(config.path.unwrap())?;
+        Ok(Dips {
+            connection,
+            scope: config.scope,
+        })
+    }
+
+    /// When the site last interacted with the user, whether it bounced
+    /// statefully and when it was last seen.
+    fn state(&self, domain: &Domain) -> (Option<f64>, bool, Option<f64>) {
+        self.connection
+            .query_row(SITE_INFO, params![domain.id], |row| {
+                Ok((
+                    row.get::<_, Option<i64>>(0)?.and_then(unix_time),
+                    row.get(1)?,
+                    unix_time(row.get(2)?),
+                ))
+            })
+            .unwrap_or((None, false, None))
+    }
+
+    /// Details and interactions of the site, out of a single query.
+    fn detail(&self, domain: &Domain) -> (Domain, DomainInteraction) {
+        let (last_interaction, bounced, last_seen) = self.state(domain);
+        let interacted = last_interaction.is_some();
+        let info = Domain {
+            id: domain.id,
+            name: domain.name.clone(),
+            entity: domain.entity.clone(),
+            tags: domain.tags.clone(),
+            prevalent: bounced && !interacted,
+            had_user_interaction: interacted,
+            last_seen,
+            last_interaction,
+            ..Default::default()
+        };
+        let interaction = DomainInteraction {
+            redirects: (bounced && !interacted) as i32,
+            ..Default::default()
+        };
+
+        (info, interaction)
+    }
+}
+
+impl StatsSource for Dips {
+    fn get_domains(&self) -> SQLiteResult<Vec<Domain>> {
+        let map_domains = |row: &Row| {
+            Ok(Domain {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                ..Default::default()
+            })
+        };
+
+        let mut stmt = self.connection.prepare(SITES)?;
+        let domains = stmt
+            .query_map(NO_PARAMS, map_domains)?
+            .filter_map(|d| d.ok())
+            .collect();
+
+        match &self.scope {
+            Some(scope) => Ok(scope.select(domains)),
+            None => Ok(domains),
+        }
+    }
+
+    fn get_info(&self, domain: &Domain) -> SQLiteResult<Domain> {
+        Ok(self.detail(domain).0)
+    }
+
+    fn domain_interaction(&self, domain: &Domain) -> DomainInteraction {
+        self.detail(domain).1
+    }
+
+    fn details(&self, domains: Vec<Domain>) -> Vec<(Domain, DomainInteraction)> {
+        domains
+            .into_iter()
+            .map(|domain| match domain.id {
+                0 => (domain, DomainInteraction::default()),
+                _ => self.detail(&domain),
+            })
+            .collect()
+    }
+
+    fn find(&self, name: &str) -> Option<Domain> {
+        self.connection
+            .query_row(SCOPED_SITES, params![name], |row| {
+                Ok(Domain {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    ..Default::default()
+                })
+            })
+            .ok()
+    }
+}
+
+fn unix_time(chromium_time: i64) -> Option<f64> {
+    match chromium_time {
+        0 => None,
+        time => Some(time as f64 / 1_000_000.0 - WINDOWS_EPOCH_OFFSET),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Jan 1st 2021 in Chromium time, microseconds since 1601.
+    const NEW_YEAR: i64 = (1_609_459_200 + 11_644_473_600) * 1_000_000;
+    const HOUR: i64 = 3600 * 1_000_000;
+
+    /// A site with its storage, interaction, stateful bounce and bounce times.
+    type Bounce<'a> = (&'a str, Option<i64>, Option<i64>, Option<i64>, Option<i64>);
+
+    /// An in-memory DIPS database holding `bounces` for `sites`.
+    fn dips(sites: &[Bounce]) -> Dips {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE bounces (site TEXT PRIMARY KEY NOT NULL,
+                    first_site_storage_time INTEGER, last_site_storage_time INTEGER,
+                    first_user_interaction_time INTEGER, last_user_interaction_time INTEGER,
+                    first_stateful_bounce_time INTEGER, last_stateful_bounce_time INTEGER,
+                    first_bounce_time INTEGER, last_bounce_time INTEGER)",
+            )
+            .unwrap();
+
+        for (site, storage, interaction, stateful_bounce, bounce) in sites {
+            connection
+                .execute(
+                    "INSERT INTO bounces (site, first_site_storage_time, last_site_storage_time, first_user_interaction_time, last_user_interaction_time, first_stateful_bounce_time, last_stateful_bounce_time, first_bounce_time, last_bounce_time) VALUES (?1, ?2, ?2, ?3, ?3, ?4, ?4, ?5, ?5)",
+                    params![site, storage, interaction, stateful_bounce, bounce],
+                )
+                .unwrap();
+        }
+
+        Dips {
+            connection,
+            scope: None,
+        }
+    }
+
+    #[test]
+    fn maps_bounces_onto_itp() {
+        let db = dips(&[
+            (
+                "tracker.example",
+                Some(NEW_YEAR),
+                None,
+                Some(NEW_YEAR + HOUR),
+                Some(NEW_YEAR + 2 * HOUR),
+            ),
+            (
+                "login.example",
+                None,
+                Some(NEW_YEAR),
+                Some(NEW_YEAR + HOUR),
+                None,
+            ),
+            ("cdn.example", Some(NEW_YEAR), None, None, Some(NEW_YEAR)),
+        ]);
+        let domains = db.get_domains().unwrap();
+        let details = db.details(domains.clone());
+        let find = |name: &str| {
+            details
+                .iter()
+                .find(|(domain, _)| domain.name == name)
+                .cloned()
+                .unwrap()
+        };
+
+        assert_eq!(domains.len(), 3);
+
+        // Bounced statefully without interaction, its state is about to be cleared.
+        let (tracker, interaction) = find("tracker.example");
+        assert!(tracker.prevalent && !tracker.had_user_interaction);
+        assert_eq!(tracker.last_interaction, None);
+        assert_eq!(tracker.last_seen, Some(1_609_459_200.0 + 2.0 * 3600.0));
+        assert_eq!(interaction.redirects, 1);
+        assert_eq!(tracker.first_party_interaction, 0);
+
+        let (login, interaction) = find("login.example");
+        assert!(!login.prevalent && login.had_user_interaction);
+        assert_eq!(login.last_interaction, Some(1_609_459_200.0));
+        assert_eq!(interaction.redirects, 0);
+
+        let (cdn, interaction) = find("cdn.example");
+        assert!(!cdn.prevalent && !cdn.had_user_interaction);
+        assert_eq!(interaction.redirects, 0);
+
+        let found = db.find("tracker.example").unwrap();
+        assert_eq!(db.get_info(&found).unwrap().prevalent, tracker.prevalent);
+        assert!(db.find("news.example").is_none());
+    }
+}
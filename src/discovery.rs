@@ -0,0 +1,615 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OpenFlags, NO_PARAMS};
+use serde::Deserialize;
+
+pub const DATABASE_PATH: &str = "Library/Containers/com.apple.Safari/Data/Library/WebKit/WebsiteData/ResourceLoadStatistics/observations.db";
+
+const DATABASE_NAME: &str = "observations.db";
+
+/// Directories WebKitGTK and WPE ports keep their ITP database in, relative to
+/// the data or cache directory of the embedding application.
+const WEBKITGTK_DIRECTORIES: &[&str] = &["itp", "ResourceLoadStatistics", "WebsiteData/itp"];
+/// GNOME Web, the WebKitGTK browser shipped by most distributions.
+const STOCK_WEBKITGTK: &str = "epiphany";
+
+const SIMULATOR_DEVICES: &str = "Library/Developer/CoreSimulator/Devices";
+const SIMULATOR_RUNTIME_PREFIX: &str = "com.apple.CoreSimulator.SimRuntime.";
+const SAFARI_BUNDLE_ID: &str = "com.apple.mobilesafari";
+const MACOS_SAFARI_BUNDLE_ID: &str = "com.apple.Safari";
+const CONTAINER_METADATA: &str = ".com.apple.mobile_container_manager.metadata.plist";
+const APP_CONTAINERS: &str = "Containers/Data/Application";
+const MACOS_CONTAINERS: &str = "Library/Containers";
+const MACOS_CONTAINER_DATA: &str = "Data";
+/// Applications outside of the sandbox keep their data under a directory named
+/// after their bundle identifier instead.
+const MACOS_UNSANDBOXED_WEBKIT: &str = "Library/WebKit";
+const UNSANDBOXED_DATABASE: &str = "WebsiteData/ResourceLoadStatistics/observations.db";
+const WEBKIT_DATABASE: &str = "Library/WebKit/WebsiteData/ResourceLoadStatistics/observations.db";
+
+/// Safari flavours installed on macOS, by container and label.
+const SAFARI_FLAVOURS: &[(&str, &str)] = &[
+    ("com.apple.Safari", "Safari"),
+    ("com.apple.SafariTechnologyPreview", "STP"),
+];
+/// Safari 17+ keeps the website data of each profile in its own store, named
+/// after the profile's identifier.
+const PROFILE_STORES: &str = "Data/Library/WebKit/WebsiteDataStore";
+const PROFILE_DATABASE: &str = "ResourceLoadStatistics/observations.db";
+const SAFARI_TABS: &str = "Data/Library/Safari/SafariTabs.db";
+const PROFILE_NAMES: &str = "SELECT external_uuid, title FROM bookmarks WHERE subtype = 2";
+
+/// Where Safari keeps its database inside a simulator's data directory, besides
+/// its own application container.
+const SIMULATOR_SAFARI_PATHS: &[&str] = &[
+    "Library/WebKit/com.apple.mobilesafari/WebsiteData/ResourceLoadStatistics/observations.db",
+    "Library/WebKit/WebsiteData/ResourceLoadStatistics/observations.db",
+];
+
+/// Friendly names of well known WebKitGTK and WPE applications.
+const KNOWN_APPLICATIONS: &[(&str, &str)] = &[
+    ("epiphany", "GNOME Web"),
+    ("cog", "Cog (WPE)"),
+    ("webkitgtk", "WebKitGTK"),
+    ("wpe", "WPE WebKit"),
+];
+
+/// Chromium based browsers keeping their user data under the platform's config
+/// directory, on macOS and Linux respectively.
+const CHROMIUM_BROWSERS: &[(&str, &str)] = &[
+    ("Google/Chrome", "Chrome"),
+    ("google-chrome", "Chrome"),
+    ("Chromium", "Chromium"),
+    ("chromium", "Chromium"),
+    ("Microsoft Edge", "Edge"),
+    ("microsoft-edge", "Edge"),
+    ("BraveSoftware/Brave-Browser", "Brave"),
+];
+/// Bounce tracking mitigation state, one database per profile.
+const DIPS_DATABASE: &str = "DIPS";
+
+/// Directories Firefox keeps its profiles in, relative to the home directory:
+/// on macOS, on Linux, and as a Snap or Flatpak application.
+const FIREFOX_PROFILES: &[&str] = &[
+    "Library/Application Support/Firefox/Profiles",
+    ".mozilla/firefox",
+    "snap/firefox/common/.mozilla/firefox",
+    ".var/app/org.mozilla.firefox/.mozilla/firefox",
+];
+const FIREFOX_DATABASE: &str = "permissions.sqlite";
+
+/// A database found on this machine.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub label: String,
+    pub path: PathBuf,
+}
+
+/// Every tracking prevention database found for the browsers of the current
+/// platform.
+pub fn candidates() -> Vec<Candidate> {
+    let home = match dirs::home_dir() {
+        Some(dir) => dir,
+        None => return Vec::new(),
+    };
+
+    let mut candidates = Vec::new();
+
+    if cfg!(target_os = "macos") {
+        candidates.extend(safari(&home));
+    } else {
+        candidates.extend(webkitgtk(&home));
+    }
+
+    candidates.extend(chromium());
+    candidates.extend(firefox(&home));
+
+    candidates
+}
+
+/// An iOS Simulator device with a Safari database.
+#[derive(Debug, Clone)]
+pub struct SimulatorDevice {
+    pub udid: String,
+    pub name: String,
+    pub runtime: String,
+    pub path: PathBuf,
+}
+
+impl SimulatorDevice {
+    pub fn candidate(&self) -> Candidate {
+        Candidate {
+            label: format!("{} ({})", self.name, self.runtime),
+            path: self.path.clone(),
+        }
+    }
+
+    fn matches(&self, query: &str) -> bool {
+        self.name.eq_ignore_ascii_case(query) || self.udid.eq_ignore_ascii_case(query)
+    }
+}
+
+/// The ITP database of an application embedding WKWebView.
+#[derive(Debug, Clone)]
+pub struct AppContainer {
+    pub bundle_id: String,
+    /// Where the application runs, macOS or a simulator device.
+    pub platform: String,
+    pub path: PathBuf,
+}
+
+impl AppContainer {
+    pub fn candidate(&self) -> Candidate {
+        Candidate {
+            label: format!("{} ({})", self.bundle_id, self.platform),
+            path: self.path.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct DevicePlist {
+    #[serde(rename = "UDID")]
+    udid: String,
+    name: String,
+    runtime: String,
+}
+
+#[derive(Deserialize)]
+struct ContainerMetadata {
+    #[serde(rename = "MCMMetadataIdentifier")]
+    identifier: String,
+}
+
+/// Every database found on this machine: browsers, iOS Simulator devices and
+/// apps embedding WKWebView.
+pub fn all() -> Vec<Candidate> {
+    candidates()
+        .into_iter()
+        .chain(simulators().iter().map(SimulatorDevice::candidate))
+        .chain(app_containers().iter().map(AppContainer::candidate))
+        .collect()
+}
+
+/// Label of the database at `path` among the `known` ones, its file name when
+/// it wasn't discovered.
+pub fn label(path: &Path, known: &[Candidate]) -> String {
+    known
+        .iter()
+        .find(|candidate| candidate.path == path)
+        .map(|candidate| candidate.label.clone())
+        .unwrap_or_else(|| match path.file_name() {
+            Some(name) if name != DATABASE_NAME => path
+                .file_stem()
+                .unwrap_or(name)
+                .to_string_lossy()
+                .into_owned(),
+            _ => path.display().to_string(),
+        })
+}
+
+/// Database to use when none is configured: the stock browser's when it
+/// exists, otherwise asking the user to pick one when several are found.
+/// Falls back to Safari's so a missing database is reported against the path
+/// users expect.
+pub fn default_path() -> PathBuf {
+    let home = match dirs::home_dir() {
+        Some(dir) => dir,
+        None => panic!("Could not infer home directory."),
+    };
+
+    if let Some(path) = stock(&home) {
+        return path;
+    }
+
+    match pick(&candidates()) {
+        Some(candidate) => candidate.path.clone(),
+        None => home.join(DATABASE_PATH),
+    }
+}
+
+/// Database of the platform's own WebKit browser, Safari on macOS and GNOME
+/// Web elsewhere, when it exists.
+fn stock(home: &Path) -> Option<PathBuf> {
+    if cfg!(target_os = "macos") {
+        return Some(home.join(DATABASE_PATH)).filter(|path| path.is_file());
+    }
+
+    let epiphany = dirs::data_dir()?.join(STOCK_WEBKITGTK);
+    WEBKITGTK_DIRECTORIES
+        .iter()
+        .map(|directory| epiphany.join(directory).join(DATABASE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// Asks which database to use when there is more than one to choose from.
+/// Scripts can't answer, the first one is used when stdin isn't a terminal.
+pub fn pick(candidates: &[Candidate]) -> Option<&Candidate> {
+    if candidates.len() < 2 {
+        return candidates.first();
+    }
+
+    if !io::stdin().is_terminal() {
+        let first = candidates.first()?;
+        eprintln!(
+            "Found several ITP databases, using {}: pass --path to pick another",
+            first.label
+        );
+        return Some(first);
+    }
+
+    println!("Found several ITP databases:");
+    print_list(candidates);
+
+    loop {
+        print!("Pick one [1-{}]: ", candidates.len());
+        io::stdout().flush().ok()?;
+
+        let mut answer = String::new();
+        if io::stdin().lock().read_line(&mut answer).ok()? == 0 {
+            return None;
+        }
+
+        match answer.trim().parse::<usize>() {
+            Ok(index) if index >= 1 && index <= candidates.len() => {
+                return candidates.get(index - 1)
+            }
+            _ => println!("Invalid choice."),
+        }
+    }
+}
+
+/// iOS Simulator devices where Safari has been used, sorted by name.
+pub fn simulators() -> Vec<SimulatorDevice> {
+    let mut simulators: Vec<SimulatorDevice> = simulator_devices()
+        .into_iter()
+        .filter_map(|(device, plist)| {
+            let path = simulator_safari_database(&device.join("data"))?;
+
+            Some(SimulatorDevice {
+                udid: plist.udid,
+                name: plist.name,
+                runtime: runtime_name(&plist.runtime),
+                path,
+            })
+        })
+        .collect();
+
+    simulators.sort_by(|a, b| a.name.cmp(&b.name).then(a.runtime.cmp(&b.runtime)));
+    simulators
+}
+
+/// Applications embedding WKWebView, on macOS and in every simulator device,
+/// sorted by bundle identifier.
+pub fn app_containers() -> Vec<AppContainer> {
+    let home = match dirs::home_dir() {
+        Some(home) => home,
+        None => return Vec::new(),
+    };
+
+    let mut containers = Vec::new();
+
+    for container in subdirectories(&home.join(MACOS_CONTAINERS)) {
+        let path = container.join(MACOS_CONTAINER_DATA).join(WEBKIT_DATABASE);
+
+        if path.is_file() {
+            containers.push(AppContainer {
+                bundle_id: file_name(&container),
+                platform: "macOS".to_owned(),
+                path,
+            });
+        }
+    }
+
+    for application in subdirectories(&home.join(MACOS_UNSANDBOXED_WEBKIT)) {
+        let path = application.join(UNSANDBOXED_DATABASE);
+
+        if path.is_file() {
+            containers.push(AppContainer {
+                bundle_id: file_name(&application),
+                platform: "macOS, unsandboxed".to_owned(),
+                path,
+            });
+        }
+    }
+
+    for (device, plist) in simulator_devices() {
+        let platform = format!("{}, {}", plist.name, runtime_name(&plist.runtime));
+
+        for container in subdirectories(&device.join("data").join(APP_CONTAINERS)) {
+            let path = container.join(WEBKIT_DATABASE);
+
+            if let (true, Some(bundle_id)) = (path.is_file(), container_identifier(&container)) {
+                containers.push(AppContainer {
+                    bundle_id,
+                    platform: platform.clone(),
+                    path,
+                });
+            }
+        }
+    }
+
+    // Safari is already listed on its own.
+    containers.retain(|c| c.bundle_id != SAFARI_BUNDLE_ID && c.bundle_id != MACOS_SAFARI_BUNDLE_ID);
+
+    containers.sort_by(|a, b| {
+        a.bundle_id
+            .cmp(&b.bundle_id)
+            .then(a.platform.cmp(&b.platform))
+    });
+    containers
+}
+
+/// Database of the application identified by `bundle_id`, asking the user to
+/// pick one when it runs in several places.
+pub fn app_container_path(bundle_id: &str) -> Option<PathBuf> {
+    let candidates: Vec<Candidate> = app_containers()
+        .iter()
+        .filter(|container| container.bundle_id.eq_ignore_ascii_case(bundle_id))
+        .map(AppContainer::candidate)
+        .collect();
+
+    pick(&candidates).map(|candidate| candidate.path.clone())
+}
+
+/// Database of the simulator named or identified by `query`, asking the user
+/// to pick one when several devices share the same name.
+pub fn simulator_path(query: &str) -> Option<PathBuf> {
+    let candidates: Vec<Candidate> = simulators()
+        .iter()
+        .filter(|device| device.matches(query))
+        .map(SimulatorDevice::candidate)
+        .collect();
+
+    pick(&candidates).map(|candidate| candidate.path.clone())
+}
+
+pub fn print_list(candidates: &[Candidate]) {
+    for (index, candidate) in candidates.iter().enumerate() {
+        println!(
+            "{:>3}. {}\n     {}",
+            index + 1,
+            candidate.label,
+            candidate.path.display()
+        );
+    }
+}
+
+/// Safari and Safari Technology Preview, along with each of their profiles.
+fn safari(home: &Path) -> Vec<Candidate> {
+    let mut candidates = Vec::new();
+
+    for (bundle_id, flavour) in SAFARI_FLAVOURS {
+        let container = home.join(MACOS_CONTAINERS).join(bundle_id);
+        let path = container.join(MACOS_CONTAINER_DATA).join(WEBKIT_DATABASE);
+
+        if path.is_file() {
+            candidates.push(Candidate {
+                label: flavour.to_string(),
+                path,
+            });
+        }
+
+        let names = profile_names(&container);
+
+        for store in subdirectories(&container.join(PROFILE_STORES)) {
+            let path = store.join(PROFILE_DATABASE);
+
+            if path.is_file() {
+                let id = file_name(&store).to_uppercase();
+                let name = names
+                    .get(&id)
+                    .cloned()
+                    .unwrap_or_else(|| id.chars().take(8).collect());
+
+                candidates.push(Candidate {
+                    label: format!("{} — {} profile", flavour, name),
+                    path,
+                });
+            }
+        }
+    }
+
+    candidates
+}
+
+/// Profile names by identifier, as shown in Safari's settings.
+fn profile_names(container: &Path) -> HashMap<String, String> {
+    let connection = match Connection::open_with_flags(
+        container.join(SAFARI_TABS),
+        OpenFlags::SQLITE_OPEN_READ_ONLY,
+    ) {
+        Ok(connection) => connection,
+        Err(_) => return HashMap::new(),
+    };
+
+    let mut stmt = match connection.prepare(PROFILE_NAMES) {
+        Ok(stmt) => stmt,
+        Err(_) => return HashMap::new(),
+    };
+
+    stmt.query_map(NO_PARAMS, |row| {
+        Ok((row.get::<_, String>(0)?.to_uppercase(), row.get(1)?))
+    })
+    .map(|rows| rows.filter_map(|r| r.ok()).collect())
+    .unwrap_or_default()
+}
+
+/// WebKitGTK and WPE browsers (GNOME Web, Cog, ...) keep one database per
+/// application under its XDG data or cache directory, Flatpak applications
+/// under their own sandboxed copies of those.
+fn webkitgtk(home: &Path) -> Vec<Candidate> {
+    let mut roots = Vec::new();
+
+    if let Some(data) = dirs::data_dir() {
+        roots.push((data, String::new()));
+    }
+    if let Some(cache) = dirs::cache_dir() {
+        roots.push((cache, " (cache)".to_owned()));
+    }
+
+    for app in subdirectories(&home.join(".var/app")) {
+        let id = file_name(&app);
+        roots.push((app.join("data"), format!(" (Flatpak {})", id)));
+        roots.push((app.join("cache"), format!(" (Flatpak {} cache)", id)));
+    }
+
+    let mut candidates = Vec::new();
+
+    for (root, origin) in roots {
+        for app in subdirectories(&root) {
+            // Browsers with several profiles nest them one level down.
+            let profiles = std::iter::once(app.clone()).chain(subdirectories(&app));
+
+            for profile in profiles {
+                for directory in WEBKITGTK_DIRECTORIES {
+                    let path = profile.join(directory).join(DATABASE_NAME);
+
+                    if path.is_file() && !candidates.iter().any(|c: &Candidate| c.path == path) {
+                        let mut name = profile
+                            .strip_prefix(&root)
+                            .map(|p| p.display().to_string())
+                            .unwrap_or_else(|_| file_name(&profile));
+
+                        if let Some((_, known)) =
+                            KNOWN_APPLICATIONS.iter().find(|(app, _)| name == *app)
+                        {
+                            name = known.to_string();
+                        }
+
+                        candidates.push(Candidate {
+                            label: format!("{}{}", name, origin),
+                            path,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    candidates
+}
+
+/// The bounce tracking database of every profile of Chromium based browsers,
+/// labelled after the profile directory such as "Chrome — Profile 1".
+fn chromium() -> Vec<Candidate> {
+    let config = match dirs::config_dir() {
+        Some(dir) => dir,
+        None => return Vec::new(),
+    };
+
+    let mut candidates = Vec::new();
+
+    for (directory, browser) in CHROMIUM_BROWSERS {
+        for profile in subdirectories(&config.join(directory)) {
+            let path = profile.join(DIPS_DATABASE);
+
+            if path.is_file() {
+                candidates.push(Candidate {
+                    label: format!("{} — {}", browser, file_name(&profile)),
+                    path,
+                });
+            }
+        }
+    }
+
+    candidates
+}
+
+/// The permissions database of every Firefox profile, labelled after the
+/// profile directory without its random prefix, such as "Firefox — default-release".
+fn firefox(home: &Path) -> Vec<Candidate> {
+    let mut candidates = Vec::new();
+
+    for directory in FIREFOX_PROFILES {
+        for profile in subdirectories(&home.join(directory)) {
+            let path = profile.join(FIREFOX_DATABASE);
+
+            if path.is_file() {
+                let directory = file_name(&profile);
+                let name = directory
+                    .split_once('.')
+                    .map_or(directory.as_str(), |(_, name)| name);
+
+                candidates.push(Candidate {
+                    label: format!("Firefox — {}", name),
+                    path,
+                });
+            }
+        }
+    }
+
+    candidates
+}
+
+fn simulator_devices() -> Vec<(PathBuf, DevicePlist)> {
+    let devices = match dirs::home_dir() {
+        Some(home) => home.join(SIMULATOR_DEVICES),
+        None => return Vec::new(),
+    };
+
+    subdirectories(&devices)
+        .into_iter()
+        .filter_map(|device| {
+            let plist = plist::from_file(device.join("device.plist")).ok()?;
+            Some((device, plist))
+        })
+        .collect()
+}
+
+fn simulator_safari_database(data: &Path) -> Option<PathBuf> {
+    let shared = SIMULATOR_SAFARI_PATHS
+        .iter()
+        .map(|path| data.join(path))
+        .find(|path| path.is_file());
+
+    shared.or_else(|| {
+        subdirectories(&data.join(APP_CONTAINERS))
+            .into_iter()
+            .filter(|container| {
+                container_identifier(container).as_deref() == Some(SAFARI_BUNDLE_ID)
+            })
+            .map(|container| container.join(WEBKIT_DATABASE))
+            .find(|path| path.is_file())
+    })
+}
+
+/// Bundle identifier of the application owning an iOS data container.
+fn container_identifier(container: &Path) -> Option<String> {
+    let metadata: ContainerMetadata = plist::from_file(container.join(CONTAINER_METADATA)).ok()?;
+    Some(metadata.identifier)
+}
+
+/// Turns `com.apple.CoreSimulator.SimRuntime.iOS-17-0` into `iOS 17.0`.
+fn runtime_name(runtime: &str) -> String {
+    let name = runtime.trim_start_matches(SIMULATOR_RUNTIME_PREFIX);
+
+    match name.find('-') {
+        Some(index) => format!("{} {}", &name[..index], name[index + 1..].replace('-', ".")),
+        None => name.to_owned(),
+    }
+}
+
+fn subdirectories(path: &Path) -> Vec<PathBuf> {
+    let mut directories: Vec<PathBuf> = fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    directories.sort();
+    directories
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
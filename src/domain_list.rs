@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use crate::blocklists::{self, Blocklist};
+use crate::database::{Domain, DomainInteraction};
+use crate::entities::Entities;
+use crate::graph::Adjacency;
+use crate::navigation::{self, Related};
+use crate::search::Search;
+use crate::sort::Sort;
+use crate::stats_source::StatsSource;
+
+/// Domains as listed in the Domains tab along with their interactions:
+/// detailed, annotated with their owning entity and blocklist tags, kept by
+/// the search filters, sorted and gathered by entity when `by_entity` is set.
+///
+/// Detailing domains takes several queries, so they are detailed once per
+/// opened database and only arranged again when the filters, the sort or the
+/// grouping change. The relationships between domains are indexed along with
+/// them.
+pub struct DomainList {
+    pub domains: Vec<Domain>,
+    pub interactions: Vec<DomainInteraction>,
+    /// Every domain in scope detailed, listed or filtered out.
+    rows: Vec<(Domain, DomainInteraction)>,
+    /// Position of every domain in scope among `rows`, by name.
+    scoped: HashMap<String, usize>,
+    /// Whether domains are annotated with their owning entity.
+    entities: bool,
+    adjacency: Adjacency,
+}
+
+impl DomainList {
+    pub fn build(
+        db: &dyn StatsSource,
+        entities: Option<&Entities>,
+        blocklists: &[Blocklist],
+        by_entity: bool,
+        search: &Search,
+        sort: &Sort,
+    ) -> Self {
+        let mut domains = db.get_domains().unwrap_or_default();
+        blocklists::annotate(blocklists, &mut domains);
+
+        if let Some(entities) = entities {
+            entities.annotate(&mut domains);
+        }
+
+        let rows = db.details(domains);
+        let mut list = DomainList {
+            domains: Vec::new(),
+            interactions: Vec::new(),
+            scoped: rows
+                .iter()
+                .enumerate()
+                .map(|(index, (domain, _))| (domain.name.clone(), index))
+                .collect(),
+            rows,
+            entities: entities.is_some(),
+            adjacency: Adjacency::new(db.relationships()),
+        };
+
+        list.arrange(by_entity, search, sort);
+        list
+    }
+
+    /// Lists the detailed domains again once the filters, the sort or the
+    /// grouping changed, without querying the database.
+    pub fn arrange(&mut self, by_entity: bool, search: &Search, sort: &Sort) {
+        let mut rows = self.rows.clone();
+
+        if search.filtering() {
+            rows.retain(|(domain, interaction)| domain.id != 0 && search.keep(domain, interaction));
+        }
+        sort.apply(&mut rows);
+
+        // Domains without a known owner come last, each entity keeping the sort.
+        if self.entities && by_entity {
+            rows.sort_by(|(a, _), (b, _)| match (&a.entity, &b.entity) {
+                (Some(a), Some(b)) => a.cmp(b),
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            });
+        }
+
+        let (domains, interactions) = rows.into_iter().unzip();
+        self.domains = domains;
+        self.interactions = interactions;
+    }
+
+    /// `selected` kept within the list, rows may have left it from under the
+    /// selection. `None` once the list is empty.
+    pub fn clamp(&self, selected: Option<usize>) -> Option<usize> {
+        match self.domains.len() {
+            0 => None,
+            len => Some(selected.unwrap_or(0).min(len - 1)),
+        }
+    }
+
+    /// Index of the domain named `name`, when listed.
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.domains.iter().position(|domain| domain.name == name)
+    }
+
+    /// Whether the domain named `name` is only hidden by the search filters,
+    /// rather than out of scope or known from relationships alone.
+    pub fn filtered_out(&self, name: &str) -> bool {
+        self.scoped.contains_key(name) && self.position(name).is_none()
+    }
+
+    /// The domain in scope named `name`, listed or filtered out.
+    pub fn scoped(&self, name: &str) -> Option<&Domain> {
+        self.scoped.get(name).map(|&index| &self.rows[index].0)
+    }
+
+    /// Domains related to the one at `selected`.
+    pub fn related(&self, selected: Option<usize>) -> Vec<Related> {
+        selected
+            .and_then(|index| self.domains.get(index))
+            .map(|domain| navigation::related(&domain.name, self.adjacency.of(&domain.name)))
+            .unwrap_or_default()
+    }
+
+    /// Every domain in scope along with its interactions, whatever the
+    /// search filters keep.
+    pub fn rows(&self) -> &[(Domain, DomainInteraction)] {
+        &self.rows
+    }
+
+    /// Every relationship between domains, listed or not.
+    pub fn adjacency(&self) -> &Adjacency {
+        &self.adjacency
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture::Fixture;
+
+    fn fixture() -> Fixture {
+        ["news.example", "shop.example", "tracker.example"]
+            .iter()
+            .fold(Fixture::new(), |fixture, name| {
+                fixture.with_domain(
+                    Domain {
+                        name: name.to_string(),
+                        prevalent: name.starts_with("tracker"),
+                        ..Default::default()
+                    },
+                    DomainInteraction::default(),
+                )
+            })
+    }
+
+    #[test]
+    fn keeps_the_selection_within_a_shrunk_list() {
+        let db = fixture();
+        let mut search = Search::default();
+        let mut list = DomainList::build(&db, None, &[], false, &search, &Sort::default());
+        assert_eq!(list.clamp(Some(2)), Some(2));
+
+        // Only the prevalent domain is left, below the selection.
+        search.toggle(0);
+        list.arrange(false, &search, &Sort::default());
+        assert_eq!(list.domains.len(), 1);
+        assert_eq!(list.clamp(Some(2)), Some(0));
+        assert!(list.filtered_out("shop.example"));
+
+        // Nothing is selected once nothing is listed.
+        search.toggle(1);
+        list.arrange(false, &search, &Sort::default());
+        assert!(list.domains.is_empty());
+        assert_eq!(list.clamp(Some(2)), None);
+
+        search.toggle(0);
+        search.toggle(1);
+        list.arrange(false, &search, &Sort::default());
+        assert_eq!(list.clamp(None), Some(0));
+    }
+
+    #[test]
+    fn keeps_filtered_out_rows() {
+        let db = fixture();
+        let mut search = Search::default();
+        let mut list = DomainList::build(&db, None, &[], false, &search, &Sort::default());
+
+        search.toggle(0);
+        list.arrange(false, &search, &Sort::default());
+        assert_eq!(list.domains.len(), 1);
+        assert_eq!(list.rows().len(), 3);
+        assert!(list.scoped("shop.example").is_some());
+        assert!(list.scoped("other.example").is_none());
+    }
+}
@@ -1,117 +1,409 @@
-use std::io::Stdout;
+use std::cmp;
 
+use chrono::DateTime;
 use tui::{
-    backend::CrosstermBackend,
+    backend::Backend,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans},
-    widgets::{Block, BorderType, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table},
+    widgets::{Block, BorderType, Borders, Cell, Paragraph, Row, Table, TableState},
     Frame,
 };
 
-use crate::database::{Database, Domain, DomainInteraction};
+use crate::cookies::{self, Cookie};
+use crate::database::{Check, Domain, DomainInteraction};
+use crate::entities::EntityTotals;
+use crate::navigation::Related;
+use crate::search::Search;
+use crate::sort::{Column, Sort};
+use crate::stats_source::{SourceInfo, StatsSource};
+use crate::website_data::{self, Entry, Kind};
+
 pub struct DomainRenderer<'d> {
     list: &'d Vec<Domain>,
     selected: Option<&'d Domain>,
+    cookies: Option<&'d [Cookie]>,
+    website_data: Option<&'d [Entry]>,
+    entity: Option<EntityTotals>,
+    blocklists: bool,
+    search: Option<&'d Search>,
+    columns: &'d [Column],
+    interactions: &'d [DomainInteraction],
+    sort: Sort,
+    related: &'d [Related],
+    /// Related domain selected, once the details have the focus.
+    related_selected: Option<usize>,
+    breadcrumbs: Option<String>,
 }
 
 impl<'d> DomainRenderer<'d> {
     pub fn new(list: &'d Vec<Domain>, selected: Option<&'d Domain>) -> Self {
-        Self { list, selected }
+        Self {
+            list,
+            selected,
+            cookies: None,
+            website_data: None,
+            entity: None,
+            blocklists: false,
+            search: None,
+            columns: &[],
+            interactions: &[],
+            sort: Sort::default(),
+            related: &[],
+            related_selected: None,
+            breadcrumbs: None,
+        }
+    }
+
+    /// Shows the cookies of the selected domain out of `cookies`.
+    pub fn with_cookies(mut self, cookies: Option<&'d [Cookie]>) -> Self {
+        self.cookies = cookies;
+        self
     }
 
-    pub fn render(
+    /// Shows the totals of the entity owning the selected domain.
+    pub fn with_entity(mut self, entity: Option<EntityTotals>) -> Self {
+        self.entity = entity;
+        self
+    }
+
+    /// Highlights the characters matching the query of `search`.
+    pub fn with_search(mut self, search: &'d Search) -> Self {
+        self.search = Some(search);
+        self
+    }
+
+    /// Lists the domains along with `columns`, out of their details and of
+    /// `interactions`, the header marking how they are sorted.
+    pub fn with_columns(
+        mut self,
+        columns: &'d [Column],
+        interactions: &'d [DomainInteraction],
+        sort: Sort,
+    ) -> Self {
+        self.columns = columns;
+        self.interactions = interactions;
+        self.sort = sort;
+        self
+    }
+
+    /// Shows the blocklists tagging the selected domain, once some are configured.
+    pub fn with_blocklists(mut self, blocklists: bool) -> Self {
+        self.blocklists = blocklists;
+        self
+    }
+
+    /// Shows what the selected domain stores out of `website_data`.
+    pub fn with_website_data(mut self, website_data: Option<&'d [Entry]>) -> Self {
+        self.website_data = website_data;
+        self
+    }
+
+    /// Lists the domains related to the selected one, highlighting
+    /// `selected` when the details have the focus.
+    pub fn with_related(mut self, related: &'d [Related], selected: Option<usize>) -> Self {
+        self.related = related;
+        self.related_selected = selected;
+        self
+    }
+
+    /// Titles the details with the domains drilled down from.
+    pub fn with_breadcrumbs(mut self, breadcrumbs: Option<String>) -> Self {
+        self.breadcrumbs = breadcrumbs;
+        self
+    }
+
+    pub fn render<B: Backend>(
         &self,
-        db: &Database,
-        screen: &mut Frame<CrosstermBackend<Stdout>>,
+        db: &dyn StatsSource,
+        screen: &mut Frame<B>,
         container: Rect,
-        list_state: &mut ListState,
+        list_state: &mut TableState,
     ) {
+        let widths = self.list_widths(self.split(container)[0].width);
+
         if self.list.is_empty() {
-            let domain_ui_panes = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(20), Constraint::Percentage(80)].as_ref())
-                .split(container);
+            let domain_ui_panes = self.split(container);
 
             screen.render_widget(self.render_empty_list_widget(), domain_ui_panes[0]);
             return;
         }
 
-        let selected = self.selected.unwrap();
+        // Scoped domains not observed yet have no details.
+        let selected_domain = match self.selected {
+            Some(domain) if domain.id != 0 => domain,
+            _ => {
+                let domain_ui_panes = self.split(container);
 
-        if selected.id == 0 {
-            let domain_ui_panes = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(20), Constraint::Percentage(80)].as_ref())
-                .split(container);
+                let domain_details_panes = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Percentage(100)].as_ref())
+                    .split(domain_ui_panes[1]);
 
-            let domain_details_panes = Layout::default()
-                .direction(Direction::Vertical)
-                .constraints([Constraint::Percentage(100)].as_ref())
-                .split(domain_ui_panes[1]);
+                screen.render_stateful_widget(
+                    self.render_list_widget(&widths),
+                    domain_ui_panes[0],
+                    list_state,
+                );
+                screen.render_widget(self.render_empty_info_widget(), domain_details_panes[0]);
 
-            screen.render_stateful_widget(
-                self.render_list_widget(),
-                domain_ui_panes[0],
-                list_state,
-            );
-            screen.render_widget(self.render_empty_info_widget(), domain_details_panes[0]);
+                return;
+            }
+        };
 
-            return;
+        let domain_ui_panes = self.split(container);
+
+        let breakdown = db.breakdown(selected_domain);
+        let mut constraints = vec![Constraint::Percentage(15)];
+
+        if self.entity.is_some() {
+            constraints.push(Constraint::Length(4));
+        }
+        if self.blocklists {
+            constraints.push(Constraint::Length(
+                3 + selected_domain.tags.len().max(1) as u16,
+            ));
+        }
+        if !breakdown.is_empty() {
+            constraints.push(Constraint::Length(8));
         }
 
-        let selected_domain = self.selected.unwrap();
-        let domain_ui_panes = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(20), Constraint::Percentage(80)].as_ref())
-            .split(container);
+        // Interactions fill the pane unless followed by related domains or
+        // storage sections, which then share the space left.
+        let sections = !self.related.is_empty() as u32
+            + self.cookies.is_some() as u32
+            + self.website_data.is_some() as u32;
+        match sections {
+            0 => constraints.push(Constraint::Min(0)),
+            _ => {
+                constraints.push(Constraint::Length(4));
+                constraints.extend((0..sections).map(|_| Constraint::Ratio(1, sections)));
+            }
+        }
 
         let domain_details_panes = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Percentage(15), Constraint::Percentage(85)].as_ref())
+            .constraints(constraints)
             .split(domain_ui_panes[1]);
+        let mut panes = domain_details_panes.into_iter();
 
-        let selected_domain_info = db
-            .get_info(&selected_domain)
-            .expect("get information from domain");
+        // Listed domains are detailed already.
+        let selected_domain_info = selected_domain.clone();
+        let domain_interaction = db.domain_interaction(selected_domain);
+        let prevalent = selected_domain_info.prevalent;
+        let had_user_interaction = selected_domain_info.had_user_interaction;
 
-        let domain_interaction = db.domain_interaction(&selected_domain);
-
-        screen.render_stateful_widget(self.render_list_widget(), domain_ui_panes[0], list_state);
+        screen.render_stateful_widget(
+            self.render_list_widget(&widths),
+            domain_ui_panes[0],
+            list_state,
+        );
         screen.render_widget(
             self.render_info_widget(selected_domain_info),
-            domain_details_panes[0],
+            panes.next().unwrap(),
         );
+
+        if let Some(entity) = &self.entity {
+            screen.render_widget(self.render_entity_widget(entity), panes.next().unwrap());
+        }
+
+        if self.blocklists {
+            screen.render_widget(
+                self.render_tags_widget(selected_domain, prevalent),
+                panes.next().unwrap(),
+            );
+        }
+
+        if !breakdown.is_empty() {
+            // One column for the row names, one per source and the total.
+            let widths =
+                vec![Constraint::Ratio(1, breakdown.len() as u32 + 2); breakdown.len() + 2];
+
+            screen.render_widget(
+                self.render_sources_widget(&breakdown, &widths),
+                panes.next().unwrap(),
+            );
+        }
+
         screen.render_widget(
             self.render_interaction_widget(domain_interaction),
-            domain_details_panes[1],
+            panes.next().unwrap(),
         );
+
+        if !self.related.is_empty() {
+            let mut related_state = TableState::default();
+            related_state.select(self.related_selected);
+
+            screen.render_stateful_widget(
+                self.render_related_widget(),
+                panes.next().unwrap(),
+                &mut related_state,
+            );
+        }
+
+        if let Some(cookies) = self.cookies {
+            let domain_cookies: Vec<&Cookie> = cookies
+                .iter()
+                .filter(|cookie| cookie.belongs_to(&selected_domain.name))
+                .collect();
+
+            screen.render_widget(
+                self.render_cookies_widget(&domain_cookies, prevalent, had_user_interaction),
+                panes.next().unwrap(),
+            );
+        }
+
+        if let Some(website_data) = self.website_data {
+            let domain_data: Vec<&Entry> = website_data
+                .iter()
+                .filter(|entry| entry.belongs_to(&selected_domain.name))
+                .collect();
+
+            screen.render_widget(
+                self.render_website_data_widget(&domain_data),
+                panes.next().unwrap(),
+            );
+        }
+    }
+
+    /// The list widens to fit its columns.
+    fn split(&self, container: Rect) -> Vec<Rect> {
+        let list_width = match self.columns.is_empty() {
+            true => 20,
+            false => 40,
+        };
+
+        Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [
+                    Constraint::Percentage(list_width),
+                    Constraint::Percentage(100 - list_width),
+                ]
+                .as_ref(),
+            )
+            .split(container)
+    }
+
+    /// Widths of the name and columns within a list `width` wide.
+    fn list_widths(&self, width: u16) -> Vec<Constraint> {
+        // Room for the title and the sort marker.
+        let columns: Vec<_> = self
+            .columns
+            .iter()
+            .map(|column| cmp::max(column.title().len() as u16 + 1, column.width()))
+            .collect();
+
+        // Fits the longest name along with its entity and tags, as long as
+        // the columns stay in view: borders and spacing aside.
+        let available = width.saturating_sub(2 + columns.iter().map(|c| c + 1).sum::<u16>());
+        let name_width = self
+            .list
+            .iter()
+            .map(|domain| self.render_name(domain).width() as u16)
+            .max()
+            .unwrap_or(0);
+        let name_width = cmp::max(
+            cmp::min(name_width, available),
+            Column::Name.title().len() as u16 + 1,
+        );
+
+        let mut widths = vec![match columns.is_empty() {
+            true => Constraint::Percentage(100),
+            false => Constraint::Length(name_width),
+        }];
+        widths.extend(columns.into_iter().map(Constraint::Length));
+        widths
     }
 
-    pub fn render_list_widget(&self) -> List<'_> {
+    pub fn render_list_widget<'w>(&'w self, widths: &'w [Constraint]) -> Table<'w> {
         let list_block = Block::default()
             .borders(Borders::ALL)
             .style(Style::default().fg(Color::White))
             .title("Domains")
             .border_type(BorderType::Plain);
 
-        let list_items: Vec<_> = self
+        let header_style = Style::default().add_modifier(Modifier::BOLD);
+        let header: Vec<_> = std::iter::once(&Column::Name)
+            .chain(self.columns.iter())
+            .map(|column| {
+                let marker = match (self.sort.column == *column, self.sort.descending) {
+                    (false, _) => "",
+                    (true, false) => "▲",
+                    (true, true) => "▼",
+                };
+                Cell::from(Span::styled(
+                    format!("{}{}", column.title(), marker),
+                    header_style,
+                ))
+            })
+            .collect();
+
+        let rows: Vec<_> = self
             .list
             .iter()
-            .map(|domain| {
-                ListItem::new(Spans::from(vec![Span::styled(
-                    domain.name.clone(),
-                    Style::default(),
-                )]))
+            .enumerate()
+            .map(|(index, domain)| {
+                let interaction = self.interactions.get(index).cloned().unwrap_or_default();
+                let mut cells = vec![Cell::from(self.render_name(domain))];
+
+                cells.extend(
+                    self.columns
+                        .iter()
+                        .map(|column| render_column(*column, domain, &interaction)),
+                );
+                Row::new(cells)
             })
             .collect();
 
-        List::new(list_items).block(list_block).highlight_style(
-            Style::default()
-                .bg(Color::Yellow)
-                .fg(Color::Black)
-                .add_modifier(Modifier::BOLD),
-        )
+        Table::new(rows)
+            .header(Row::new(header))
+            .block(list_block)
+            .widths(widths)
+            .highlight_style(
+                Style::default()
+                    .bg(Color::Yellow)
+                    .fg(Color::Black)
+                    .add_modifier(Modifier::BOLD),
+            )
+    }
+
+    /// The domain name, its characters matching the search highlighted,
+    /// followed by its entity and blocklist tags.
+    fn render_name(&self, domain: &Domain) -> Spans<'d> {
+        let matches = self
+            .search
+            .and_then(|search| search.matches(&domain.name))
+            .unwrap_or_default();
+        let mut spans: Vec<_> = domain
+            .name
+            .chars()
+            .enumerate()
+            .map(|(position, c)| match matches.contains(&position) {
+                true => Span::styled(
+                    c.to_string(),
+                    Style::default()
+                        .fg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                ),
+                false => Span::raw(c.to_string()),
+            })
+            .collect();
+
+        if let Some(entity) = &domain.entity {
+            spans.push(Span::styled(
+                format!(" · {}", entity),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        if !domain.tags.is_empty() {
+            let tags: Vec<_> = domain.tags.iter().map(|tag| tag.short()).collect();
+            spans.push(Span::styled(
+                format!(" [{}]", tags.join(", ")),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        Spans::from(spans)
     }
 
     fn render_info_widget(&self, domain: Domain) -> Table<'_> {
@@ -121,6 +413,7 @@ impl<'d> DomainRenderer<'d> {
             Cell::from(Span::raw(domain.id.to_string())),
             Cell::from(Span::raw(domain.is_prevalent().to_owned())),
             Cell::from(Span::raw(domain.is_very_prevalent().to_owned())),
+            Cell::from(Span::raw(domain.had_user_interaction().to_owned())),
             Cell::from(Span::raw(domain.first_party_interaction.to_string())),
             Cell::from(Span::raw(domain.first_party_store_access.to_string())),
         ])])
@@ -128,6 +421,7 @@ impl<'d> DomainRenderer<'d> {
             Cell::from(Span::styled("ID", header_style)),
             Cell::from(Span::styled("PREVALENT?", header_style)),
             Cell::from(Span::styled("VERY PREVALENT?", header_style)),
+            Cell::from(Span::styled("INTERACTED?", header_style)),
             Cell::from(Span::styled("1ST PARTY USER INTERACTIONS", header_style)),
             Cell::from(Span::styled("ACCESS DUE TO STORAGE API", header_style)),
         ]))
@@ -135,29 +429,337 @@ impl<'d> DomainRenderer<'d> {
             Block::default()
                 .borders(Borders::ALL)
                 .style(Style::default().fg(Color::White))
-                .title("Details")
+                .title(match &self.breadcrumbs {
+                    Some(breadcrumbs) => format!("Details — {}", breadcrumbs),
+                    None => "Details".to_owned(),
+                })
                 .border_type(BorderType::Plain),
         )
         .widths(&[
             Constraint::Percentage(10),
             Constraint::Percentage(10),
+            Constraint::Percentage(15),
+            Constraint::Percentage(15),
+            Constraint::Percentage(25),
+            Constraint::Percentage(25),
+        ])
+    }
+
+    /// Interactions of every listed domain owned by the same entity.
+    fn render_entity_widget(&self, entity: &EntityTotals) -> Table<'d> {
+        let header_style = Style::default().add_modifier(Modifier::BOLD);
+
+        Table::new(vec![Row::new(vec![
+            Cell::from(Span::raw(entity.name.clone())),
+            Cell::from(Span::raw(entity.domains.to_string())),
+            Cell::from(Span::raw(entity.interaction.iframes.to_string())),
+            Cell::from(Span::raw(entity.interaction.requests.to_string())),
+            Cell::from(Span::raw(entity.interaction.redirects.to_string())),
+        ])])
+        .header(Row::new(vec![
+            Cell::from(Span::styled("ENTITY", header_style)),
+            Cell::from(Span::styled("DOMAINS", header_style)),
+            Cell::from(Span::styled("IFRAMED", header_style)),
+            Cell::from(Span::styled("CROSS ORIGIN REQUESTS TO", header_style)),
+            Cell::from(Span::styled(
+                "REDIRECTS WITHOUT USER INTERACTION",
+                header_style,
+            )),
+        ]))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title("Entity")
+                .border_type(BorderType::Plain),
+        )
+        .widths(&[
             Constraint::Percentage(20),
-            Constraint::Percentage(30),
+            Constraint::Percentage(10),
+            Constraint::Percentage(15),
+            Constraint::Percentage(25),
             Constraint::Percentage(30),
         ])
     }
 
+    /// Blocklists tagging the domain, titled after whether ITP's prevalence
+    /// verdict agrees with them.
+    fn render_tags_widget(&self, domain: &Domain, prevalent: bool) -> Table<'d> {
+        let header_style = Style::default().add_modifier(Modifier::BOLD);
+
+        let (agrees, verdict) = match (!domain.tags.is_empty(), prevalent) {
+            (true, true) => (true, "listed and prevalent, ITP agrees"),
+            (true, false) => (false, "listed but not prevalent"),
+            (false, true) => (false, "prevalent but not listed"),
+            (false, false) => (true, "neither listed nor prevalent, ITP agrees"),
+        };
+
+        let rows: Vec<_> = match domain.tags.is_empty() {
+            true => vec![Row::new(vec![Cell::from(Span::raw("Not listed"))])],
+            false => domain
+                .tags
+                .iter()
+                .map(|tag| {
+                    Row::new(vec![
+                        Cell::from(Span::raw(tag.list.clone())),
+                        Cell::from(Span::raw(tag.category.clone().unwrap_or_default())),
+                    ])
+                })
+                .collect(),
+        };
+
+        Table::new(rows)
+            .header(Row::new(vec![
+                Cell::from(Span::styled("LIST", header_style)),
+                Cell::from(Span::styled("CATEGORY", header_style)),
+            ]))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(match agrees {
+                        true => Color::White,
+                        false => Color::Yellow,
+                    }))
+                    .title(format!("Blocklists — {}", verdict))
+                    .border_type(BorderType::Plain),
+            )
+            .widths(&[Constraint::Percentage(40), Constraint::Percentage(60)])
+    }
+
+    /// Classification of the domain in each aggregated database, along with how
+    /// many of them agree.
+    fn render_sources_widget<'w>(
+        &self,
+        breakdown: &[SourceInfo],
+        widths: &'w [Constraint],
+    ) -> Table<'w> {
+        let header_style = Style::default().add_modifier(Modifier::BOLD);
+        let checks: [(&str, Check); 4] = [
+            ("OBSERVED?", |_| true),
+            ("PREVALENT?", |d| d.prevalent),
+            ("VERY PREVALENT?", |d| d.very_prevalent),
+            ("INTERACTED?", |d| d.had_user_interaction),
+        ];
+
+        let rows: Vec<Row> = checks
+            .iter()
+            .map(|(title, check)| {
+                let values: Vec<bool> = breakdown
+                    .iter()
+                    .map(|source| source.info.as_ref().is_some_and(check))
+                    .collect();
+                let total = values.iter().filter(|value| **value).count();
+
+                let mut cells = vec![Cell::from(Span::styled(*title, header_style))];
+                cells.extend(values.iter().map(|value| {
+                    Cell::from(Span::raw(match value {
+                        true => "Yes",
+                        false => "No",
+                    }))
+                }));
+                cells.push(Cell::from(Span::raw(format!(
+                    "{} of {}",
+                    total,
+                    breakdown.len()
+                ))));
+
+                Row::new(cells)
+            })
+            .collect();
+
+        let mut header = vec![Cell::from(Span::raw(""))];
+        header.extend(
+            breakdown
+                .iter()
+                .map(|source| Cell::from(Span::styled(source.label.clone(), header_style))),
+        );
+        header.push(Cell::from(Span::styled("TOTAL", header_style)));
+
+        Table::new(rows)
+            .header(Row::new(header))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::White))
+                    .title("Sources")
+                    .border_type(BorderType::Plain),
+            )
+            .widths(widths)
+    }
+
+    /// Cookies set for the domain or its subdomains, flagging the ones whose
+    /// lifetime matches one of ITP's caps.
+    fn render_cookies_widget(
+        &self,
+        cookies: &[&Cookie],
+        prevalent: bool,
+        had_user_interaction: bool,
+    ) -> Table<'d> {
+        let header_style = Style::default().add_modifier(Modifier::BOLD);
+        let cap = |cookie: &Cookie| {
+            cookie
+                .cap(prevalent, had_user_interaction)
+                .map(str::to_owned)
+        };
+        let capped = cookies.iter().filter(|c| cap(c).is_some()).count();
+
+        let rows: Vec<Row> = cookies
+            .iter()
+            .map(|cookie| {
+                let cap = cap(cookie);
+                let style = match cap {
+                    Some(_) => Style::default().fg(Color::Yellow),
+                    None => Style::default(),
+                };
+
+                Row::new(vec![
+                    Cell::from(Span::raw(cookie.name.clone())),
+                    Cell::from(Span::raw(cookie.domain.clone())),
+                    Cell::from(Span::raw(cookie.path.clone())),
+                    Cell::from(Span::raw(cookie.flags())),
+                    Cell::from(Span::raw(cookies::format_time(cookie.creation))),
+                    Cell::from(Span::raw(cookies::format_time(cookie.expiry))),
+                    Cell::from(Span::raw(cap.unwrap_or_default())),
+                ])
+                .style(style)
+            })
+            .collect();
+
+        Table::new(rows)
+            .header(Row::new(vec![
+                Cell::from(Span::styled("NAME", header_style)),
+                Cell::from(Span::styled("DOMAIN", header_style)),
+                Cell::from(Span::styled("PATH", header_style)),
+                Cell::from(Span::styled("FLAGS", header_style)),
+                Cell::from(Span::styled("CREATED", header_style)),
+                Cell::from(Span::styled("EXPIRES", header_style)),
+                Cell::from(Span::styled("ITP CAP", header_style)),
+            ]))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::White))
+                    .title(format!("Cookies ({}, {} capped)", cookies.len(), capped))
+                    .border_type(BorderType::Plain),
+            )
+            .widths(&[
+                Constraint::Percentage(20),
+                Constraint::Percentage(15),
+                Constraint::Percentage(10),
+                Constraint::Percentage(13),
+                Constraint::Percentage(15),
+                Constraint::Percentage(15),
+                Constraint::Percentage(12),
+            ])
+    }
+
+    /// Domains the selected one iframes, loads or redirects to, and the other
+    /// way around.
+    fn render_related_widget(&self) -> Table<'d> {
+        let header_style = Style::default().add_modifier(Modifier::BOLD);
+
+        let rows: Vec<Row> = self
+            .related
+            .iter()
+            .map(|related| {
+                Row::new(vec![
+                    Cell::from(Span::raw(related.relation.clone())),
+                    Cell::from(Span::raw(related.domain.clone())),
+                ])
+            })
+            .collect();
+
+        Table::new(rows)
+            .header(Row::new(vec![
+                Cell::from(Span::styled("RELATION", header_style)),
+                Cell::from(Span::styled("DOMAIN", header_style)),
+            ]))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::White))
+                    .title(format!("Related domains ({})", self.related.len()))
+                    .border_type(BorderType::Plain),
+            )
+            .widths(&[Constraint::Percentage(30), Constraint::Percentage(70)])
+            .highlight_style(
+                Style::default()
+                    .bg(Color::Yellow)
+                    .fg(Color::Black)
+                    .add_modifier(Modifier::BOLD),
+            )
+    }
+
+    /// Data stored by the domain or its subdomains, summed up per kind and host.
+    fn render_website_data_widget(&self, entries: &[&Entry]) -> Table<'d> {
+        let header_style = Style::default().add_modifier(Modifier::BOLD);
+
+        let mut totals: Vec<(Kind, &str, u64)> = Vec::new();
+        for entry in entries {
+            match totals
+                .iter_mut()
+                .find(|(kind, host, _)| *kind == entry.kind && *host == entry.host)
+            {
+                Some((_, _, size)) => *size += entry.size,
+                None => totals.push((entry.kind, &entry.host, entry.size)),
+            }
+        }
+
+        let total: u64 = totals.iter().map(|(_, _, size)| size).sum();
+        let rows: Vec<Row> = totals
+            .iter()
+            .map(|(kind, host, size)| {
+                Row::new(vec![
+                    Cell::from(Span::raw(kind.name().to_owned())),
+                    Cell::from(Span::raw(host.to_string())),
+                    Cell::from(Span::raw(match size {
+                        0 => "-".to_owned(),
+                        _ => website_data::format_size(*size),
+                    })),
+                ])
+            })
+            .collect();
+
+        Table::new(rows)
+            .header(Row::new(vec![
+                Cell::from(Span::styled("KIND", header_style)),
+                Cell::from(Span::styled("ORIGIN", header_style)),
+                Cell::from(Span::styled("SIZE", header_style)),
+            ]))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::White))
+                    .title(format!(
+                        "Website data ({})",
+                        website_data::format_size(total)
+                    ))
+                    .border_type(BorderType::Plain),
+            )
+            .widths(&[
+                Constraint::Percentage(20),
+                Constraint::Percentage(50),
+                Constraint::Percentage(30),
+            ])
+    }
+
     fn render_empty_list_widget(&self) -> Paragraph<'d> {
+        let (message, hint) = match self.search.is_some_and(Search::filtering) {
+            true => (
+                "No domain matches the filters.",
+                "Press 'f' to edit the filter or toggle the chips with the number keys.",
+            ),
+            false => (
+                "The domain database is currently empty.",
+                "Navigate to a domain of interest in Safari to initialize it.",
+            ),
+        };
+
         Paragraph::new(vec![
             Spans::from(vec![Span::raw("")]),
-            Spans::from(vec![Span::styled(
-                "The domain database is currently empty.",
-                Style::default().fg(Color::Red),
-            )]),
+            Spans::from(vec![Span::styled(message, Style::default().fg(Color::Red))]),
             Spans::from(vec![Span::raw("")]),
-            Spans::from(vec![Span::raw(
-                "Navigate to a domain of interest in Safari to initialize it.",
-            )]),
+            Spans::from(vec![Span::raw(hint)]),
         ])
         .alignment(Alignment::Center)
         .block(
@@ -221,3 +823,147 @@ impl<'d> DomainRenderer<'d> {
         ])
     }
 }
+
+fn render_column<'d>(column: Column, domain: &Domain, interaction: &DomainInteraction) -> Cell<'d> {
+    let count = |count: i32| Cell::from(Span::raw(count.to_string()));
+    let date = |time: Option<f64>| Cell::from(Span::raw(time.map(format_date).unwrap_or_default()));
+
+    match column {
+        Column::Name => Cell::from(Span::raw(domain.name.clone())),
+        Column::Prevalence => match (domain.very_prevalent, domain.prevalent) {
+            (true, _) => Cell::from(Span::styled("VP", Style::default().fg(Color::Red))),
+            (false, true) => Cell::from(Span::styled("P", Style::default().fg(Color::Yellow))),
+            (false, false) => Cell::from(""),
+        },
+        Column::Iframes => count(interaction.iframes),
+        Column::Requests => count(interaction.requests),
+        Column::Redirects => count(interaction.redirects),
+        Column::LastSeen => date(domain.last_seen),
+        Column::LastInteraction => date(domain.last_interaction),
+    }
+}
+
+/// Formats seconds since the Unix epoch as a UTC day.
+fn format_date(time: f64) -> String {
+    DateTime::from_timestamp(time as i64, 0)
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use tui::{backend::TestBackend, Terminal};
+
+    use super::*;
+    use crate::fixture::Fixture;
+    use crate::navigation;
+    use crate::stats_source::Relation;
+
+    fn fixture() -> Fixture {
+        Fixture::new()
+            .with_domain(
+                Domain {
+                    name: "news.example".to_owned(),
+                    had_user_interaction: true,
+                    first_party_interaction: 4,
+                    ..Default::default()
+                },
+                DomainInteraction::default(),
+            )
+            .with_domain(
+                Domain {
+                    name: "tracker.example".to_owned(),
+                    prevalent: true,
+                    ..Default::default()
+                },
+                DomainInteraction {
+                    iframes: 2,
+                    requests: 5,
+                    redirects: 1,
+                },
+            )
+            .with_relationship(Relation::Iframe, "tracker.example", "news.example")
+    }
+
+    /// Detailed domains of `db`, as listed by the Domains tab.
+    fn list(db: &dyn StatsSource) -> Vec<Domain> {
+        db.get_domains()
+            .unwrap()
+            .iter()
+            .map(|domain| db.get_info(domain).unwrap())
+            .collect()
+    }
+
+    /// Lines of the screen once `renderer` drew the domain at `selected`.
+    fn draw(renderer: DomainRenderer, db: &dyn StatsSource, selected: usize) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(140, 30)).unwrap();
+        let mut state = TableState::default();
+        state.select(Some(selected));
+
+        terminal
+            .draw(|screen| renderer.render(db, screen, screen.size(), &mut state))
+            .unwrap();
+
+        let buffer = terminal.backend().buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol.as_str())
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn contains(screen: &[String], text: &str) -> bool {
+        screen.iter().any(|line| line.contains(text))
+    }
+
+    #[test]
+    fn lists_domains_and_details_of_the_selected_one() {
+        let db = fixture();
+        let list = list(&db);
+        let screen = draw(DomainRenderer::new(&list, list.get(1)), &db, 1);
+
+        assert!(contains(&screen, "news.example"));
+        assert!(contains(&screen, "tracker.example"));
+        assert!(screen
+            .iter()
+            .any(|line| line.contains("│2 ") && line.contains("Yes")));
+        assert!(contains(&screen, "Interactions"));
+        assert!(screen
+            .iter()
+            .any(|line| line.contains("│2 ") && line.contains(" 5 ") && line.contains(" 1 ")));
+    }
+
+    #[test]
+    fn lists_the_related_domains_and_breadcrumbs() {
+        let db = fixture();
+        let list = list(&db);
+        let related = navigation::related("tracker.example", &db.relationships());
+        let screen = draw(
+            DomainRenderer::new(&list, list.get(1))
+                .with_related(&related, None)
+                .with_breadcrumbs(Some("news.example › tracker.example".to_owned())),
+            &db,
+            1,
+        );
+
+        assert!(contains(
+            &screen,
+            "Details — news.example › tracker.example"
+        ));
+        assert!(contains(&screen, "Related domains (1)"));
+        assert!(screen
+            .iter()
+            .any(|line| line.contains("iframed under") && line.contains("news.example")));
+    }
+
+    #[test]
+    fn explains_an_empty_list() {
+        let db = Fixture::new();
+        let list = list(&db);
+        let screen = draw(DomainRenderer::new(&list, None), &db, 0);
+
+        assert!(contains(&screen, "No domains available"));
+    }
+}
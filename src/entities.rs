@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::database::{Domain, DomainInteraction};
+
+/// Disconnect's `entities.json` format, which lists the domains owned by
+/// each organization:
+///
+/// ```json
+/// { "entities": { "Facebook": { "properties": ["facebook.com"], "resources": ["fbcdn.net"] } } }
+/// ```
+#[derive(Deserialize)]
+struct EntitiesFile {
+    entities: HashMap<String, EntityFile>,
+}
+
+#[derive(Deserialize)]
+struct EntityFile {
+    #[serde(default)]
+    properties: Vec<String>,
+    #[serde(default)]
+    resources: Vec<String>,
+}
+
+/// Owning organization of each known domain.
+#[derive(Debug, Default)]
+pub struct Entities {
+    owners: HashMap<String, String>,
+}
+
+impl Entities {
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let file: EntitiesFile = serde_json::from_str(&fs::read_to_string(path)?)?;
+        let mut owners = HashMap::new();
+
+        for (name, entity) in file.entities {
+            for domain in entity.properties.into_iter().chain(entity.resources) {
+                owners.insert(domain.to_lowercase(), name.clone());
+            }
+        }
+
+        Ok(Entities { owners })
+    }
+
+    /// Owner of `domain` or of the closest parent domain listed.
+    pub fn owner(&self, domain: &str) -> Option<&str> {
+        let mut candidate = domain;
+
+        loop {
+            if let Some(owner) = self.owners.get(candidate) {
+                return Some(owner);
+            }
+
+            candidate = candidate.split_once('.')?.1;
+        }
+    }
+
+    pub fn annotate(&self, domains: &mut [Domain]) {
+        for domain in domains.iter_mut() {
+            domain.entity = self.owner(&domain.name).map(str::to_owned);
+        }
+    }
+}
+
+/// Combined interactions of the domains in scope owned by an entity, listed
+/// or filtered out by the search.
+pub struct EntityTotals {
+    pub name: String,
+    pub domains: usize,
+    pub interaction: DomainInteraction,
+}
+
+impl EntityTotals {
+    pub fn compute(name: &str, rows: &[(Domain, DomainInteraction)]) -> Self {
+        let mut totals = EntityTotals {
+            name: name.to_owned(),
+            domains: 0,
+            interaction: DomainInteraction::default(),
+        };
+
+        for (_, interaction) in rows
+            .iter()
+            .filter(|(d, _)| d.id != 0 && d.entity.as_deref() == Some(name))
+        {
+            totals.domains += 1;
+            totals.interaction.iframes += interaction.iframes;
+            totals.interaction.requests += interaction.requests;
+            totals.interaction.redirects += interaction.redirects;
+        }
+
+        totals
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entities(json: &str) -> Entities {
+        let path = crate::fixture::temporary_path("entities.json");
+        fs::write(&path, json).unwrap();
+        let entities = Entities::from_file(&path);
+        fs::remove_file(&path).unwrap();
+        entities.unwrap()
+    }
+
+    fn domain(id: i64, name: &str, entity: Option<&str>) -> Domain {
+        Domain {
+            id,
+            name: name.to_owned(),
+            entity: entity.map(str::to_owned),
+            ..Default::default()
+        }
+    }
+
+    fn interaction(iframes: i32, requests: i32, redirects: i32) -> DomainInteraction {
+        DomainInteraction {
+            iframes,
+            requests,
+            redirects,
+        }
+    }
+
+    #[test]
+    fn finds_owners_of_properties_resources_and_subdomains() {
+        let entities = entities(
+            r#"{ "entities": {
+                "Facebook": { "properties": ["Facebook.com"], "resources": ["fbcdn.net"] },
+                "Example": { "properties": ["example.com"] },
+                "Empty": {}
+            } }"#,
+        );
+
+        assert_eq!(entities.owner("facebook.com"), Some("Facebook"));
+        assert_eq!(entities.owner("static.xx.fbcdn.net"), Some("Facebook"));
+        assert_eq!(entities.owner("example.com"), Some("Example"));
+        assert_eq!(entities.owner("notexample.com"), None);
+        assert_eq!(entities.owner("com"), None);
+
+        let mut domains = vec![
+            domain(1, "www.example.com", None),
+            domain(2, "other.org", Some("Stale")),
+        ];
+        entities.annotate(&mut domains);
+        assert_eq!(domains[0].entity.as_deref(), Some("Example"));
+        assert_eq!(domains[1].entity, None);
+    }
+
+    #[test]
+    fn rejects_malformed_or_missing_entity_lists() {
+        let path = crate::fixture::temporary_path("malformed-entities.json");
+        fs::write(&path, r#"{ "entities": [] }"#).unwrap();
+        assert!(Entities::from_file(&path).is_err());
+        fs::remove_file(&path).unwrap();
+
+        assert!(Entities::from_file(&path).is_err());
+    }
+
+    #[test]
+    fn totals_the_domains_of_an_entity() {
+        let rows = vec![
+            (
+                domain(1, "facebook.com", Some("Facebook")),
+                interaction(1, 2, 3),
+            ),
+            (
+                domain(2, "fbcdn.net", Some("Facebook")),
+                interaction(4, 5, 6),
+            ),
+            (
+                domain(3, "example.com", Some("Example")),
+                interaction(10, 10, 10),
+            ),
+            (domain(4, "other.org", None), interaction(10, 10, 10)),
+            // Known from relationships alone.
+            (
+                domain(0, "fb.me", Some("Facebook")),
+                interaction(10, 10, 10),
+            ),
+        ];
+
+        let totals = EntityTotals::compute("Facebook", &rows);
+        assert_eq!(totals.name, "Facebook");
+        assert_eq!(totals.domains, 2);
+        assert_eq!(totals.interaction.iframes, 5);
+        assert_eq!(totals.interaction.requests, 7);
+        assert_eq!(totals.interaction.redirects, 9);
+
+        let totals = EntityTotals::compute("Unknown", &rows);
+        assert_eq!(totals.domains, 0);
+        assert_eq!(totals.interaction.requests, 0);
+    }
+}
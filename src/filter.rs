@@ -0,0 +1,695 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::database::{Domain, DomainInteraction};
+
+/// Fields of `Domain` and `DomainInteraction` usable in filters, with their type.
+const FIELDS: [(&str, Field); 10] = [
+    ("name", Field::Text(Text::Name)),
+    ("entity", Field::Text(Text::Entity)),
+    ("prevalent", Field::Flag(Flag::Prevalent)),
+    ("very_prevalent", Field::Flag(Flag::VeryPrevalent)),
+    ("interaction", Field::Flag(Flag::Interaction)),
+    ("interactions", Field::Number(Number::Interactions)),
+    ("storage_access", Field::Number(Number::StorageAccess)),
+    ("iframes", Field::Number(Number::Iframes)),
+    ("requests", Field::Number(Number::Requests)),
+    ("redirects", Field::Number(Number::Redirects)),
+];
+
+#[derive(Debug)]
+pub struct FilterError {
+    message: String,
+    /// Column of the offending token, starting at 1.
+    column: usize,
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.column)
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+#[derive(Debug, Clone, Copy)]
+enum Field {
+    Flag(Flag),
+    Number(Number),
+    Text(Text),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Flag {
+    Prevalent,
+    VeryPrevalent,
+    Interaction,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Number {
+    Interactions,
+    StorageAccess,
+    Iframes,
+    Requests,
+    Redirects,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Text {
+    Name,
+    Entity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Matches,
+}
+
+impl Comparison {
+    fn holds<T: PartialOrd>(self, left: T, right: T) -> bool {
+        match self {
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Matches => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Bool(bool),
+    Flag(Flag),
+    Number(Number, Comparison, i64),
+    Text(Text, Comparison, String),
+    Regex(Text, Regex),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+/// A parsed filter expression such as
+/// `prevalent && iframes > 5 && !interaction && name ~ "cdn"`.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    source: String,
+    expr: Expr,
+}
+
+impl Filter {
+    /// Parses `source`, resolving `@name` references out of `named`.
+    pub fn parse(source: &str, named: &BTreeMap<String, String>) -> Result<Self, FilterError> {
+        let expr = Parser::new(source, named, Vec::new())?.parse()?;
+
+        Ok(Filter {
+            source: source.to_owned(),
+            expr,
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether a domain, out of its details and interactions, passes the filter.
+    pub fn matches(&self, info: &Domain, interaction: &DomainInteraction) -> bool {
+        evaluate(&self.expr, info, interaction)
+    }
+}
+
+fn evaluate(expr: &Expr, info: &Domain, interaction: &DomainInteraction) -> bool {
+    match expr {
+        Expr::Bool(value) => *value,
+        Expr::Flag(flag) => flag_value(*flag, info),
+        Expr::Number(number, comparison, value) => {
+            let field = match number {
+                Number::Interactions => info.first_party_interaction as i64,
+                Number::StorageAccess => info.first_party_store_access as i64,
+                Number::Iframes => interaction.iframes as i64,
+                Number::Requests => interaction.requests as i64,
+                Number::Redirects => interaction.redirects as i64,
+            };
+            comparison.holds(field, *value)
+        }
+        Expr::Text(text, comparison, value) => {
+            comparison.holds(text_value(*text, info).to_lowercase(), value.to_lowercase())
+        }
+        Expr::Regex(text, regex) => regex.is_match(text_value(*text, info)),
+        Expr::Not(expr) => !evaluate(expr, info, interaction),
+        Expr::And(left, right) => {
+            evaluate(left, info, interaction) && evaluate(right, info, interaction)
+        }
+        Expr::Or(left, right) => {
+            evaluate(left, info, interaction) || evaluate(right, info, interaction)
+        }
+    }
+}
+
+fn flag_value(flag: Flag, info: &Domain) -> bool {
+    match flag {
+        Flag::Prevalent => info.prevalent,
+        Flag::VeryPrevalent => info.very_prevalent,
+        Flag::Interaction => info.had_user_interaction,
+    }
+}
+
+fn text_value(text: Text, info: &Domain) -> &str {
+    match text {
+        Text::Name => &info.name,
+        Text::Entity => info.entity.as_deref().unwrap_or(""),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Reference(String),
+    Number(i64),
+    Str(String),
+    Compare(Comparison),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+    End,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Ident(name) => write!(f, "`{}`", name),
+            Token::Reference(name) => write!(f, "`@{}`", name),
+            Token::Number(value) => write!(f, "`{}`", value),
+            Token::Str(value) => write!(f, "\"{}\"", value),
+            Token::Compare(_) => write!(f, "comparison"),
+            Token::And => write!(f, "`&&`"),
+            Token::Or => write!(f, "`||`"),
+            Token::Not => write!(f, "`!`"),
+            Token::Open => write!(f, "`(`"),
+            Token::Close => write!(f, "`)`"),
+            Token::End => write!(f, "end of the filter"),
+        }
+    }
+}
+
+fn error(message: impl Into<String>, column: usize) -> FilterError {
+    FilterError {
+        message: message.into(),
+        column,
+    }
+}
+
+/// Splits `source` into tokens along with their column.
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, FilterError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let column = i + 1;
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        let (token, length) = match (c, next) {
+            (c, _) if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ('&', Some('&')) => (Token::And, 2),
+            ('|', Some('|')) => (Token::Or, 2),
+            ('=', Some('=')) => (Token::Compare(Comparison::Equal), 2),
+            ('!', Some('=')) => (Token::Compare(Comparison::NotEqual), 2),
+            ('<', Some('=')) => (Token::Compare(Comparison::LessOrEqual), 2),
+            ('>', Some('=')) => (Token::Compare(Comparison::GreaterOrEqual), 2),
+            ('<', _) => (Token::Compare(Comparison::Less), 1),
+            ('>', _) => (Token::Compare(Comparison::Greater), 1),
+            ('~', _) => (Token::Compare(Comparison::Matches), 1),
+            ('!', _) => (Token::Not, 1),
+            ('(', _) => (Token::Open, 1),
+            (')', _) => (Token::Close, 1),
+            ('&', _) | ('|', _) => {
+                return Err(error(
+                    format!("`{}` must be doubled, as in `{}{}`", c, c, c),
+                    column,
+                ))
+            }
+            ('=', _) => return Err(error("use `==` to compare", column)),
+            ('"', _) => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|c| *c == '"')
+                    .ok_or_else(|| error("unterminated string", column))?;
+                let value = chars[i + 1..i + 1 + end].iter().collect();
+                (Token::Str(value), end + 2)
+            }
+            (c, _) if c.is_ascii_digit() => {
+                let length = chars[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+                let value: String = chars[i..i + length].iter().collect();
+                let value = value
+                    .parse()
+                    .map_err(|_| error(format!("{} is too large", value), column))?;
+                (Token::Number(value), length)
+            }
+            ('@', _) => {
+                let length = 1 + word_length(&chars[i + 1..]);
+                let name: String = chars[i + 1..i + length].iter().collect();
+
+                if name.is_empty() {
+                    return Err(error("expected a filter name after `@`", column));
+                }
+                (Token::Reference(name), length)
+            }
+            (c, _) if c.is_alphabetic() || c == '_' => {
+                let length = word_length(&chars[i..]);
+                (Token::Ident(chars[i..i + length].iter().collect()), length)
+            }
+            (c, _) => return Err(error(format!("unexpected `{}`", c), column)),
+        };
+
+        tokens.push((token, column));
+        i += length;
+    }
+
+    tokens.push((Token::End, chars.len() + 1));
+    Ok(tokens)
+}
+
+fn word_length(chars: &[char]) -> usize {
+    chars
+        .iter()
+        .take_while(|c| c.is_alphanumeric() || **c == '_' || **c == '-')
+        .count()
+}
+
+/// Recursive descent over the grammar:
+///
+/// ```text
+/// or      = and ("||" and)*
+/// and     = unary ("&&" unary)*
+/// unary   = "!" unary | primary
+/// primary = "(" or ")" | "@" name | "true" | "false" | field [comparison value]
+/// ```
+struct Parser<'n> {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    named: &'n BTreeMap<String, String>,
+    /// Named filters being resolved, to catch the ones referencing themselves.
+    resolving: Vec<String>,
+}
+
+impl<'n> Parser<'n> {
+    fn new(
+        source: &str,
+        named: &'n BTreeMap<String, String>,
+        resolving: Vec<String>,
+    ) -> Result<Self, FilterError> {
+        Ok(Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+            named,
+            resolving,
+        })
+    }
+
+    fn parse(mut self) -> Result<Expr, FilterError> {
+        if self.peek() == &Token::End {
+            return Err(error("the filter is empty", 1));
+        }
+
+        let expr = self.or()?;
+
+        match self.next() {
+            (Token::End, _) => Ok(expr),
+            (Token::Close, column) => Err(error("unmatched `)`", column)),
+            (token, column) => Err(error(
+                format!("expected `&&` or `||` before {}", token),
+                column,
+            )),
+        }
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    fn next(&mut self) -> (Token, usize) {
+        let token = self.tokens[self.position].clone();
+
+        if token.0 != Token::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn or(&mut self) -> Result<Expr, FilterError> {
+        let mut expr = self.and()?;
+
+        while self.peek() == &Token::Or {
+            self.next();
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, FilterError> {
+        let mut expr = self.unary()?;
+
+        while self.peek() == &Token::And {
+            self.next();
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, FilterError> {
+        match self.peek() {
+            Token::Not => {
+                self.next();
+                Ok(Expr::Not(Box::new(self.unary()?)))
+            }
+            _ => self.primary(),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, FilterError> {
+        match self.next() {
+            (Token::Open, column) => {
+                let expr = self.or()?;
+
+                match self.next() {
+                    (Token::Close, _) => Ok(expr),
+                    (_, _) => Err(error("unmatched `(`", column)),
+                }
+            }
+            (Token::Reference(name), column) => self.reference(&name, column),
+            (Token::Ident(name), _) if name == "true" => Ok(Expr::Bool(true)),
+            (Token::Ident(name), _) if name == "false" => Ok(Expr::Bool(false)),
+            (Token::Ident(name), column) => self.field(&name, column),
+            (token, column) => Err(error(format!("expected a field, found {}", token), column)),
+        }
+    }
+
+    fn reference(&self, name: &str, column: usize) -> Result<Expr, FilterError> {
+        let source = self
+            .named
+            .get(name)
+            .ok_or_else(|| error(format!("no filter named `{}`", name), column))?;
+
+        if self.resolving.iter().any(|resolving| resolving == name) {
+            return Err(error(format!("`@{}` references itself", name), column));
+        }
+
+        let mut resolving = self.resolving.clone();
+        resolving.push(name.to_owned());
+
+        // Errors point at the column within the named filter.
+        Parser::new(source, self.named, resolving)
+            .and_then(Parser::parse)
+            .map_err(|e| error(format!("in `@{}`, {}", name, e.message), e.column))
+    }
+
+    fn field(&mut self, name: &str, column: usize) -> Result<Expr, FilterError> {
+        let field = FIELDS
+            .iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, field)| *field)
+            .ok_or_else(|| {
+                let fields: Vec<_> = FIELDS.iter().map(|(name, _)| *name).collect();
+                error(
+                    format!(
+                        "unknown field `{}`, expected one of {}",
+                        name,
+                        fields.join(", ")
+                    ),
+                    column,
+                )
+            })?;
+
+        let comparison = match self.peek() {
+            Token::Compare(comparison) => *comparison,
+            _ => {
+                return match field {
+                    Field::Flag(flag) => Ok(Expr::Flag(flag)),
+                    _ => Err(error(
+                        format!("`{}` must be compared to a value", name),
+                        column,
+                    )),
+                }
+            }
+        };
+        let (_, operator_column) = self.next();
+        let (value, value_column) = self.next();
+
+        match (field, comparison, value) {
+            (Field::Flag(flag), Comparison::Equal | Comparison::NotEqual, Token::Ident(value))
+                if value == "true" || value == "false" =>
+            {
+                let expr = Expr::Flag(flag);
+                match (comparison == Comparison::Equal) == (value == "true") {
+                    true => Ok(expr),
+                    false => Ok(Expr::Not(Box::new(expr))),
+                }
+            }
+            (Field::Flag(_), _, _) => Err(error(
+                format!("`{}` is true or false, use `{}` or `!{}`", name, name, name),
+                operator_column,
+            )),
+            (Field::Number(_), Comparison::Matches, _) => Err(error(
+                format!("`{}` is a number, `~` only applies to text", name),
+                operator_column,
+            )),
+            (Field::Number(number), comparison, Token::Number(value)) => {
+                Ok(Expr::Number(number, comparison, value))
+            }
+            (Field::Number(_), _, token) => Err(error(
+                format!("`{}` is a number, found {}", name, token),
+                value_column,
+            )),
+            (Field::Text(text), Comparison::Matches, Token::Str(pattern)) => {
+                RegexBuilder::new(&pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map(|regex| Expr::Regex(text, regex))
+                    .map_err(|e| {
+                        // Syntax errors span several lines, ending with the cause.
+                        let message = e.to_string();
+                        let cause = message.lines().next_back().unwrap_or_default();
+                        error(
+                            format!("invalid pattern, {}", cause.trim_start_matches("error: ")),
+                            value_column,
+                        )
+                    })
+            }
+            (Field::Text(text), Comparison::Equal | Comparison::NotEqual, Token::Str(value)) => {
+                Ok(Expr::Text(text, comparison, value))
+            }
+            (Field::Text(_), Comparison::Equal | Comparison::NotEqual | Comparison::Matches, _) => {
+                Err(error(
+                    format!("`{}` is text, expected a quoted string", name),
+                    value_column,
+                ))
+            }
+            (Field::Text(_), _, _) => Err(error(
+                format!("`{}` is text, compare it with `==`, `!=` or `~`", name),
+                operator_column,
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named() -> BTreeMap<String, String> {
+        let mut named = BTreeMap::new();
+        named.insert(
+            "trackers".to_owned(),
+            "prevalent && !interaction".to_owned(),
+        );
+        named.insert("loop".to_owned(), "@loop".to_owned());
+        named.insert("broken".to_owned(), "iframes >".to_owned());
+        named
+    }
+
+    fn tracker() -> (Domain, DomainInteraction) {
+        (
+            Domain {
+                id: 1,
+                name: "cdn.tracker.example".to_owned(),
+                prevalent: true,
+                entity: Some("Tracker Inc".to_owned()),
+                ..Default::default()
+            },
+            DomainInteraction {
+                iframes: 3,
+                requests: 10,
+                redirects: 1,
+            },
+        )
+    }
+
+    fn news() -> (Domain, DomainInteraction) {
+        (
+            Domain {
+                id: 2,
+                name: "news.example".to_owned(),
+                had_user_interaction: true,
+                first_party_interaction: 4,
+                ..Default::default()
+            },
+            DomainInteraction::default(),
+        )
+    }
+
+    /// Which of the tracker and news domains `source` keeps.
+    fn keeps(source: &str) -> (bool, bool) {
+        let filter = Filter::parse(source, &named()).unwrap();
+        let matches =
+            |(info, interaction): (Domain, DomainInteraction)| filter.matches(&info, &interaction);
+
+        (matches(tracker()), matches(news()))
+    }
+
+    fn error(source: &str) -> String {
+        Filter::parse(source, &named()).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(
+            keeps("interaction || prevalent && iframes > 5"),
+            (false, true)
+        );
+        assert_eq!(
+            keeps("(interaction || prevalent) && iframes > 2"),
+            (true, false)
+        );
+        assert_eq!(
+            keeps("prevalent || interaction && iframes > 2"),
+            (true, false)
+        );
+    }
+
+    #[test]
+    fn negates_the_closest_operand() {
+        assert_eq!(keeps("!interaction"), (true, false));
+        assert_eq!(keeps("!!interaction"), (false, true));
+        assert_eq!(keeps("!prevalent && iframes > 0"), (false, false));
+        assert_eq!(keeps("!(prevalent && iframes > 5)"), (true, true));
+        assert_eq!(keeps("interaction == false"), (true, false));
+        assert_eq!(keeps("interaction != false"), (false, true));
+        assert_eq!(keeps("@trackers"), (true, false));
+    }
+
+    #[test]
+    fn matches_text_ignoring_case() {
+        assert_eq!(keeps(r#"name ~ "^CDN\.""#), (true, false));
+        assert_eq!(keeps(r#"entity ~ "inc""#), (true, false));
+        assert_eq!(keeps(r#"name ~ "example$""#), (true, true));
+        assert_eq!(keeps(r#"name == "NEWS.example""#), (false, true));
+        assert_eq!(keeps(r#"entity != "Tracker Inc""#), (false, true));
+    }
+
+    #[test]
+    fn compares_numbers() {
+        assert_eq!(keeps("iframes >= 3"), (true, false));
+        assert_eq!(keeps("iframes > 3"), (false, false));
+        assert_eq!(keeps("requests <= 10"), (true, true));
+        assert_eq!(keeps("requests < 10"), (false, true));
+        assert_eq!(keeps("redirects == 1"), (true, false));
+        assert_eq!(keeps("redirects != 1"), (false, true));
+        assert_eq!(keeps("interactions > 3"), (false, true));
+        assert_eq!(keeps("storage_access == 0"), (true, true));
+    }
+
+    #[test]
+    fn explains_syntax_errors() {
+        assert_eq!(error(""), "the filter is empty at column 1");
+        assert_eq!(
+            error("prevalent & iframes"),
+            "`&` must be doubled, as in `&&` at column 11"
+        );
+        assert_eq!(error("iframes = 2"), "use `==` to compare at column 9");
+        assert_eq!(error(r#"name ~ "cdn"#), "unterminated string at column 8");
+        assert_eq!(
+            error("iframes > 99999999999999999999"),
+            "99999999999999999999 is too large at column 11"
+        );
+        assert_eq!(error("@"), "expected a filter name after `@` at column 1");
+        assert_eq!(error("iframes > 2 $"), "unexpected `$` at column 13");
+        assert_eq!(error("prevalent)"), "unmatched `)` at column 10");
+        assert_eq!(error("(prevalent"), "unmatched `(` at column 1");
+        assert_eq!(
+            error("prevalent interaction"),
+            "expected `&&` or `||` before `interaction` at column 11"
+        );
+        assert_eq!(
+            error("prevalent && 5"),
+            "expected a field, found `5` at column 14"
+        );
+    }
+
+    #[test]
+    fn explains_references_errors() {
+        assert_eq!(error("@missing"), "no filter named `missing` at column 1");
+        assert_eq!(
+            error("@loop"),
+            "in `@loop`, `@loop` references itself at column 1"
+        );
+        assert_eq!(
+            error("prevalent && @broken"),
+            "in `@broken`, `iframes` is a number, found end of the filter at column 10"
+        );
+    }
+
+    #[test]
+    fn explains_field_errors() {
+        assert_eq!(
+            error("iframe > 2"),
+            "unknown field `iframe`, expected one of name, entity, prevalent, very_prevalent, \
+             interaction, interactions, storage_access, iframes, requests, redirects at column 1"
+        );
+        assert_eq!(
+            error("iframes"),
+            "`iframes` must be compared to a value at column 1"
+        );
+        assert_eq!(
+            error("prevalent > 1"),
+            "`prevalent` is true or false, use `prevalent` or `!prevalent` at column 11"
+        );
+        assert_eq!(
+            error(r#"iframes ~ "a""#),
+            "`iframes` is a number, `~` only applies to text at column 9"
+        );
+        assert_eq!(
+            error(r#"iframes > "a""#),
+            r#"`iframes` is a number, found "a" at column 11"#
+        );
+        assert_eq!(
+            error(r#"name ~ "(""#),
+            "invalid pattern, unclosed group at column 8"
+        );
+        assert_eq!(
+            error("name == cdn"),
+            "`name` is text, expected a quoted string at column 9"
+        );
+        assert_eq!(
+            error(r#"name > "a""#),
+            "`name` is text, compare it with `==`, `!=` or `~` at column 6"
+        );
+    }
+}
@@ -0,0 +1,376 @@
+use std::collections::BTreeMap;
+
+use rusqlite::{params, Connection, Result as SQLiteResult, NO_PARAMS};
+
+use crate::database::{Domain, DomainInteraction};
+use crate::scope::{self, Scope};
+use crate::stats_source::StatsSource;
+use crate::Config;
+
+const PERMISSIONS: &str = "SELECT origin, type FROM moz_perms WHERE permission = 1 AND (type = ? OR type LIKE ? || '%' OR type LIKE ? || '%')";
+/// Whether the bounce tracking protection store was attached.
+const HAS_BOUNCE_TRACKING: &str =
+    "SELECT count(*) FROM pragma_database_list WHERE name = 'bounce_tracking'";
+const BOUNCE_TRACKING: &str = "SELECT siteHost, entryType, timeStamp FROM bounce_tracking.sites";
+const ATTACH_BOUNCE_TRACKING: &str = "ATTACH DATABASE ? AS bounce_tracking";
+
+/// Recorded on the site the user interacted with, lifting its restrictions.
+const USER_INTERACTION: &str = "storageAccessAPI";
+/// Recorded on the top level origin, followed by the origin granted access.
+const STORAGE_ACCESS: &str = "3rdPartyStorage^";
+const FRAME_STORAGE_ACCESS: &str = "3rdPartyFrameStorage^";
+
+/// Sibling of `permissions.sqlite` in the profile directory, Firefox 127+.
+const BOUNCE_TRACKING_DATABASE: &str = "bounce-tracking-protection.sqlite";
+const BOUNCE_TRACKER: i64 = 1;
+const USER_ACTIVATION: i64 = 0;
+
+/// Firefox's anti-tracking state, read from a profile's `permissions.sqlite`
+/// along with its bounce tracking protection store when there is one.
+///
+/// Permissions are kept per origin rather than per domain, so sites are
+/// gathered from the registrable domain of their host: Storage Access API
+/// grants count as storage access, frame grants as iframes, and sites
+/// classified as bounce trackers are shown as prevalent.
+///
+/// Sites are read once when connecting, reconnecting picks up later changes.
+pub struct Firefox {
+    sites: BTreeMap<String, Site>,
+    scope: Option<Scope>,
+}
+
+/// What Firefox knows about a site.
+#[derive(Default, Clone)]
+struct Site {
+    /// Position of the site in name order, sites having no id of their own.
+    id: i64,
+    interacted: bool,
+    bounce_tracker: bool,
+    storage_access: i32,
+    frame_storage_access: i32,
+    /// When the entry of the bounce tracking protection store was recorded.
+    recorded: Option<f64>,
+}
+
+impl Firefox {
+    /// Table telling a Firefox permissions database apart.
+    pub const TABLE: &'static str = "moz_perms";
+
+    pub fn connect(config: Config) -> SQLiteResult<Self> {
+        let path = config.path.unwrap();
+        let connection = Connection::open(&path)?;
+
+        let bounce_tracking = path.with_file_name(BOUNCE_TRACKING_DATABASE);
+        if bounce_tracking.is_file() {
+            connection.execute(
+                ATTACH_BOUNCE_TRACKING,
+                params![bounce_tracking.to_string_lossy()],
+            )?;
+        }
+
+        Ok(Firefox {
+            sites: sites(&connection)?,
+            scope: config.scope,
+        })
+    }
+
+    fn site(&self, domain: &Domain) -> Site {
+        self.sites.get(&domain.name).cloned().unwrap_or_default()
+    }
+}
+
+fn sites(connection: &Connection) -> SQLiteResult<BTreeMap<String, Site>> {
+    let mut sites: BTreeMap<String, Site> = BTreeMap::new();
+
+    let mut stmt = connection.prepare(PERMISSIONS)?;
+    let permissions = stmt.query_map(
+        params![USER_INTERACTION, STORAGE_ACCESS, FRAME_STORAGE_ACCESS],
+        |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+    )?;
+
+    for (origin, kind) in permissions.filter_map(|p| p.ok()) {
+        if kind == USER_INTERACTION {
+            if let Some(domain) = site_domain(&origin) {
+                sites.entry(domain).or_default().interacted = true;
+            }
+        } else if let Some(granted) = kind.strip_prefix(STORAGE_ACCESS).and_then(site_domain) {
+            sites.entry(granted).or_default().storage_access += 1;
+        } else if let Some(granted) = kind
+            .strip_prefix(FRAME_STORAGE_ACCESS)
+            .and_then(site_domain)
+        {
+            sites.entry(granted).or_default().frame_storage_access += 1;
+        }
+    }
+
+    let has_bounce_tracking: i64 =
+        connection.query_row(HAS_BOUNCE_TRACKING, NO_PARAMS, |row| row.get(0))?;
+
+    if has_bounce_tracking > 0 {
+        let mut stmt = connection.prepare(BOUNCE_TRACKING)?;
+        let entries = stmt.query_map(NO_PARAMS, |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })?;
+
+        for (host, kind, time) in entries.filter_map(|e| e.ok()) {
+            let site = sites.entry(scope::registrable_domain(&host)).or_default();
+            // Microseconds since the Unix epoch, the latest entry of the
+            // domain's hosts being kept.
+            let recorded = Some(time as f64 / 1_000_000.0).filter(|time| *time > 0.0);
+            site.recorded = site.recorded.into_iter().chain(recorded).reduce(f64::max);
+            match kind {
+                BOUNCE_TRACKER => site.bounce_tracker = true,
+                USER_ACTIVATION => site.interacted = true,
+                _ => {}
+            }
+        }
+    }
+
+    for (index, site) in sites.values_mut().enumerate() {
+        site.id = index as i64 + 1;
+    }
+
+    Ok(sites)
+}
+
+impl StatsSource for Firefox {
+    fn get_domains(&self) -> SQLiteResult<Vec<Domain>> {
+        let domains = self
+            .sites
+            .iter()
+            .map(|(name, site)| Domain {
+                id: site.id,
+                name: name.clone(),
+                ..Default::default()
+            })
+            .collect();
+
+        match &self.scope {
+            Some(scope) => Ok(scope.select(domains)),
+            None => Ok(domains),
+        }
+    }
+
+    fn get_info(&self, domain: &Domain) -> SQLiteResult<Domain> {
+        let site = self.site(domain);
+
+        Ok(Domain {
+            id: domain.id,
+            name: domain.name.clone(),
+            entity: domain.entity.clone(),
+            tags: domain.tags.clone(),
+            prevalent: site.bounce_tracker,
+            had_user_interaction: site.interacted,
+            first_party_store_access: site.storage_access,
+            last_seen: site.recorded,
+            last_interaction: site.recorded.filter(|_| site.interacted),
+            ..Default::default()
+        })
+    }
+
+    fn domain_interaction(&self, domain: &Domain) -> DomainInteraction {
+        let site = self.site(domain);
+
+        DomainInteraction {
+            iframes: site.frame_storage_access,
+            redirects: site.bounce_tracker as i32,
+            ..Default::default()
+        }
+    }
+
+    /// The observed domain named `name`, regardless of the configured scope.
+    fn find(&self, name: &str) -> Option<Domain> {
+        self.sites.get(name).map(|site| Domain {
+            id: site.id,
+            name: name.to_owned(),
+            ..Default::default()
+        })
+    }
+}
+
+/// Registrable domain of an origin such as
+/// `https://www.example.com:8443^userContextId=1` or `http://[::1]:8080`,
+/// its origin attributes left out.
+fn site_domain(origin: &str) -> Option<String> {
+    let origin = origin.split('^').next()?;
+    if !origin.contains("://") {
+        return None;
+    }
+
+    Some(scope::registrable_domain(origin)).filter(|domain| !domain.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Profile directory removed once dropped.
+    struct Profile(PathBuf);
+
+    impl Drop for Profile {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// A profile's `permissions.sqlite` granting `permissions`, origin and
+    /// type, along with a bounce tracking protection store holding `entries`
+    /// when there are any.
+    fn profile(name: &str, permissions: &[(&str, &str)], entries: &[(&str, i64, i64)]) -> Profile {
+        let profile = Profile(crate::fixture::temporary_path(name));
+        let _ = fs::remove_dir_all(&profile.0);
+        fs::create_dir_all(&profile.0).unwrap();
+
+        let path = profile.0.join("permissions.sqlite");
+        let connection = Connection::open(&path).unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE moz_perms (id INTEGER PRIMARY KEY, origin TEXT, type TEXT,
+                    permission INTEGER, expireType INTEGER, expireTime INTEGER,
+                    modificationTime INTEGER)",
+            )
+            .unwrap();
+        for (origin, kind) in permissions {
+            connection
+                .execute(
+                    "INSERT INTO moz_perms (origin, type, permission) VALUES (?, ?, 1)",
+                    params![origin, kind],
+                )
+                .unwrap();
+        }
+        // Denied permissions are left out.
+        connection
+            .execute(
+                "INSERT INTO moz_perms (origin, type, permission) VALUES ('https://denied.example', 'storageAccessAPI', 2)",
+                NO_PARAMS,
+            )
+            .unwrap();
+
+        if !entries.is_empty() {
+            let connection = Connection::open(profile.0.join(BOUNCE_TRACKING_DATABASE)).unwrap();
+            connection
+                .execute_batch(
+                    "CREATE TABLE sites (originAttributeSuffix TEXT, siteHost TEXT,
+                        entryType INTEGER, timeStamp INTEGER)",
+                )
+                .unwrap();
+            for (host, kind, time) in entries {
+                connection
+                    .execute(
+                        "INSERT INTO sites (originAttributeSuffix, siteHost, entryType, timeStamp) VALUES ('', ?, ?, ?)",
+                        params![host, kind, time],
+                    )
+                    .unwrap();
+            }
+        }
+
+        profile
+    }
+
+    fn connect(profile: &Profile) -> Firefox {
+        Firefox::connect(Config {
+            path: Some(profile.0.join("permissions.sqlite")),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn reduces_origins_to_their_registrable_domain() {
+        assert_eq!(
+            site_domain("https://www.example.com:8443^userContextId=1"),
+            Some("example.com".to_owned())
+        );
+        assert_eq!(site_domain("http://[::1]:8080"), Some("::1".to_owned()));
+        assert_eq!(
+            site_domain("http://192.168.1.1:8080^firstPartyDomain=example.com"),
+            Some("192.168.1.1".to_owned())
+        );
+        assert_eq!(site_domain("www.example.com"), None);
+        assert_eq!(site_domain("https://"), None);
+    }
+
+    #[test]
+    fn maps_permissions() {
+        let profile = profile(
+            "firefox_permissions",
+            &[
+                ("https://www.login.example", USER_INTERACTION),
+                (
+                    "https://news.example",
+                    "3rdPartyStorage^https://cdn.example",
+                ),
+                (
+                    "https://shop.example",
+                    "3rdPartyStorage^https://cdn.example",
+                ),
+                (
+                    "https://news.example",
+                    "3rdPartyFrameStorage^https://widget.example",
+                ),
+                ("http://[::1]:8080", USER_INTERACTION),
+                ("https://news.example", "geo"),
+            ],
+            &[],
+        );
+        let firefox = connect(&profile);
+        let names: Vec<String> = firefox
+            .get_domains()
+            .unwrap()
+            .into_iter()
+            .map(|domain| domain.name)
+            .collect();
+        assert_eq!(
+            names,
+            ["::1", "cdn.example", "login.example", "widget.example"]
+        );
+
+        let login = firefox.find("login.example").unwrap();
+        assert!(firefox.get_info(&login).unwrap().had_user_interaction);
+        assert!(!firefox.get_info(&login).unwrap().prevalent);
+
+        let cdn = firefox.find("cdn.example").unwrap();
+        assert_eq!(firefox.get_info(&cdn).unwrap().first_party_store_access, 2);
+        assert!(!firefox.get_info(&cdn).unwrap().had_user_interaction);
+
+        let widget = firefox.find("widget.example").unwrap();
+        assert_eq!(firefox.domain_interaction(&widget).iframes, 1);
+        assert_eq!(firefox.domain_interaction(&widget).redirects, 0);
+
+        assert!(firefox.find("denied.example").is_none());
+    }
+
+    #[test]
+    fn maps_bounce_tracking() {
+        let profile = profile(
+            "firefox_bounce_tracking",
+            &[("https://login.example", USER_INTERACTION)],
+            &[
+                ("tracker.example", BOUNCE_TRACKER, 1_609_459_200_000_000),
+                ("ads.tracker.example", BOUNCE_TRACKER, 1_609_462_800_000_000),
+                ("login.example", USER_ACTIVATION, 1_609_459_200_000_000),
+            ],
+        );
+        let firefox = connect(&profile);
+
+        // Hosts are gathered by domain, the latest entry being kept.
+        let tracker = firefox.find("tracker.example").unwrap();
+        let info = firefox.get_info(&tracker).unwrap();
+        assert!(info.prevalent && !info.had_user_interaction);
+        assert_eq!(info.last_seen, Some(1_609_462_800.0));
+        assert_eq!(info.last_interaction, None);
+        assert_eq!(firefox.domain_interaction(&tracker).redirects, 1);
+
+        let login = firefox.find("login.example").unwrap();
+        let info = firefox.get_info(&login).unwrap();
+        assert!(!info.prevalent && info.had_user_interaction);
+        assert_eq!(info.last_interaction, Some(1_609_459_200.0));
+        assert_eq!(firefox.domain_interaction(&login).redirects, 0);
+    }
+}
@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+
+use rusqlite::{Error as SQLiteError, Result as SQLiteResult};
+
+use crate::database::{Domain, DomainInteraction};
+use crate::stats_source::{Relation, Relationship, StatsSource};
+
+/// A path under the temporary directory unique to this run, removed first.
+pub fn temporary_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("itp_tldr-test-{}-{}", std::process::id(), name));
+    crate::database::remove(&path);
+    path
+}
+
+/// In-memory statistics, handy to exercise the UI without Safari's database.
+#[derive(Default)]
+pub struct Fixture {
+    domains: Vec<(Domain, DomainInteraction)>,
+    relationships: Vec<Relationship>,
+}
+
+impl Fixture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a domain, its id is assigned in insertion order starting at 1.
+    pub fn with_domain(mut self, mut domain: Domain, interaction: DomainInteraction) -> Self {
+        domain.id = self.domains.len() as i64 + 1;
+        self.domains.push((domain, interaction));
+        self
+    }
+
+    /// Records `from` relating to `to`, as in "tracker.example iframed under news.example".
+    pub fn with_relationship(mut self, relation: Relation, from: &str, to: &str) -> Self {
+        self.relationships.push(Relationship {
+            relation,
+            from: from.to_owned(),
+            to: to.to_owned(),
+        });
+        self
+    }
+
+    fn find(&self, domain: &Domain) -> Option<&(Domain, DomainInteraction)> {
+        self.domains.iter().find(|(d, _)| d.id == domain.id)
+    }
+}
+
+impl StatsSource for Fixture {
+    fn get_domains(&self) -> SQLiteResult<Vec<Domain>> {
+        Ok(self
+            .domains
+            .iter()
+            .map(|(domain, _)| Domain {
+                id: domain.id,
+                name: domain.name.clone(),
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    fn get_info(&self, domain: &Domain) -> SQLiteResult<Domain> {
+        self.find(domain)
+            .map(|(d, _)| d.clone())
+            .ok_or(SQLiteError::QueryReturnedNoRows)
+    }
+
+    fn domain_interaction(&self, domain: &Domain) -> DomainInteraction {
+        self.find(domain)
+            .map(|(_, i)| i.clone())
+            .unwrap_or_default()
+    }
+
+    fn relationships(&self) -> Vec<Relationship> {
+        self.relationships.clone()
+    }
+}
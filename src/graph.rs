@@ -0,0 +1,260 @@
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::{FRAC_PI_2, TAU};
+
+use crate::stats_source::{Relation, Relationship};
+
+/// Deepest neighborhood the Graph tab draws, the diagram being unreadable past it.
+pub const MAX_DEPTH: usize = 3;
+
+/// A domain placed on the diagram, its layer being its distance to the center.
+#[derive(Debug)]
+pub struct Node {
+    pub name: String,
+    pub depth: usize,
+    pub x: f64,
+    pub y: f64,
+    angle: f64,
+}
+
+#[derive(Debug)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub relation: Relation,
+}
+
+/// Relationships indexed by the domains at either of their ends, built once
+/// along with the domain list rather than searched every frame.
+#[derive(Debug, Default)]
+pub struct Adjacency(HashMap<String, Vec<Relationship>>);
+
+impl Adjacency {
+    pub fn new(relationships: Vec<Relationship>) -> Self {
+        let mut adjacency: HashMap<String, Vec<Relationship>> = HashMap::new();
+
+        for relationship in relationships {
+            if relationship.from != relationship.to {
+                adjacency
+                    .entry(relationship.to.clone())
+                    .or_default()
+                    .push(relationship.clone());
+            }
+            adjacency
+                .entry(relationship.from.clone())
+                .or_default()
+                .push(relationship);
+        }
+
+        Adjacency(adjacency)
+    }
+
+    /// Relationships from or to the domain named `name`.
+    pub fn of(&self, name: &str) -> &[Relationship] {
+        self.0.get(name).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+/// Domains within a few relationships of a center one, laid out in
+/// concentric layers.
+#[derive(Debug)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+impl Graph {
+    /// Domains at most `depth` relationships away from `center`, whichever
+    /// their direction, along with every relationship between them.
+    pub fn neighborhood(center: &str, adjacency: &Adjacency, depth: usize) -> Self {
+        let mut nodes = vec![Node {
+            name: center.to_owned(),
+            depth: 0,
+            x: 0.0,
+            y: 0.0,
+            angle: FRAC_PI_2,
+        }];
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        index.insert(center, 0);
+
+        let mut frontier = vec![0];
+
+        for layer in 1..=depth {
+            // Neighbors of the previous layer, along with the node reaching them.
+            let mut reached: Vec<(usize, &str)> = Vec::new();
+
+            for &parent in &frontier {
+                for relationship in adjacency.of(&nodes[parent].name) {
+                    let name = &nodes[parent].name;
+                    let neighbor = match (&relationship.from, &relationship.to) {
+                        (from, to) if from == name => to,
+                        (from, to) if to == name => from,
+                        _ => continue,
+                    };
+
+                    if !index.contains_key(neighbor.as_str()) {
+                        index.insert(neighbor, nodes.len() + reached.len());
+                        reached.push((parent, neighbor));
+                    }
+                }
+            }
+
+            // Placed in reaching order, nodes get the index they were given.
+            frontier = place(&mut nodes, &reached, layer);
+        }
+
+        // Relationships between two nodes show up under both of them.
+        let mut edges: Vec<Edge> = Vec::new();
+        let mut drawn: HashSet<(usize, usize, Relation)> = HashSet::new();
+
+        for node in &nodes {
+            for relationship in adjacency.of(&node.name) {
+                let from = index.get(relationship.from.as_str());
+                let to = index.get(relationship.to.as_str());
+
+                if let (Some(&from), Some(&to)) = (from, to) {
+                    if from != to && drawn.insert((from, to, relationship.relation)) {
+                        edges.push(Edge {
+                            from,
+                            to,
+                            relation: relationship.relation,
+                        });
+                    }
+                }
+            }
+        }
+
+        Graph { nodes, edges }
+    }
+
+    /// Half the height of the diagram, room for the outer layer and its labels.
+    pub fn radius(&self) -> f64 {
+        self.nodes.iter().map(|node| node.depth).max().unwrap_or(0) as f64 + 0.6
+    }
+}
+
+/// Spreads the nodes `reached` around a circle as far from the center as
+/// `layer`, next to their parents to keep edges from crossing, and returns
+/// their indexes.
+fn place(nodes: &mut Vec<Node>, reached: &[(usize, &str)], layer: usize) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..reached.len()).collect();
+    order.sort_by(|&a, &b| {
+        let (a_parent, a_name) = reached[a];
+        let (b_parent, b_name) = reached[b];
+
+        nodes[a_parent]
+            .angle
+            .total_cmp(&nodes[b_parent].angle)
+            .then_with(|| a_name.cmp(b_name))
+    });
+
+    let mut angles = vec![0.0; reached.len()];
+    for (position, &node) in order.iter().enumerate() {
+        angles[node] = FRAC_PI_2 + TAU * position as f64 / reached.len() as f64;
+    }
+
+    let first = nodes.len();
+    for (&(_, name), angle) in reached.iter().zip(angles) {
+        nodes.push(Node {
+            name: name.to_owned(),
+            depth: layer,
+            x: layer as f64 * angle.cos(),
+            y: layer as f64 * angle.sin(),
+            angle,
+        });
+    }
+
+    (first..nodes.len()).collect()
+}
+
+/// Depth and panning of the Graph tab.
+#[derive(Debug)]
+pub struct View {
+    pub depth: usize,
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        View {
+            depth: 1,
+            x: 0.0,
+            y: 0.0,
+        }
+    }
+}
+
+impl View {
+    /// Distance panned by each arrow key press, a quarter of a layer.
+    const STEP: f64 = 0.25;
+
+    pub fn deeper(&mut self) {
+        self.depth = (self.depth + 1).min(MAX_DEPTH);
+    }
+
+    pub fn shallower(&mut self) {
+        self.depth = self.depth.saturating_sub(1).max(1);
+    }
+
+    /// Moves the diagram by `dx` and `dy` steps.
+    pub fn pan(&mut self, dx: i8, dy: i8) {
+        self.x += f64::from(dx) * Self::STEP;
+        self.y += f64::from(dy) * Self::STEP;
+    }
+
+    pub fn recenter(&mut self) {
+        self.x = 0.0;
+        self.y = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relationship(relation: Relation, from: &str, to: &str) -> Relationship {
+        Relationship {
+            relation,
+            from: from.to_owned(),
+            to: to.to_owned(),
+        }
+    }
+
+    #[test]
+    fn gathers_the_neighborhood_once() {
+        let adjacency = Adjacency::new(vec![
+            relationship(Relation::Iframe, "tracker.example", "news.example"),
+            relationship(Relation::Subresource, "tracker.example", "news.example"),
+            relationship(Relation::Iframe, "tracker.example", "shop.example"),
+            relationship(Relation::Redirect, "shop.example", "login.example"),
+            relationship(Relation::Iframe, "ads.example", "ads.example"),
+        ]);
+
+        let graph = Graph::neighborhood("news.example", &adjacency, 1);
+        let names: Vec<_> = graph.nodes.iter().map(|node| node.name.as_str()).collect();
+        assert_eq!(names, ["news.example", "tracker.example"]);
+        assert_eq!(graph.edges.len(), 2);
+
+        let graph = Graph::neighborhood("news.example", &adjacency, 3);
+        let depths: Vec<_> = graph
+            .nodes
+            .iter()
+            .map(|node| (node.name.as_str(), node.depth))
+            .collect();
+        assert_eq!(
+            depths,
+            [
+                ("news.example", 0),
+                ("tracker.example", 1),
+                ("shop.example", 2),
+                ("login.example", 3)
+            ]
+        );
+        assert_eq!(graph.edges.len(), 4);
+
+        // Relationships of a domain with itself draw no edge.
+        assert!(Graph::neighborhood("ads.example", &adjacency, 1)
+            .edges
+            .is_empty());
+    }
+}
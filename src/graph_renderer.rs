@@ -0,0 +1,195 @@
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Style},
+    text::{Span, Spans},
+    widgets::{
+        canvas::{Canvas, Line},
+        Block, BorderType, Borders, Paragraph,
+    },
+    Frame,
+};
+
+use crate::domain_list::DomainList;
+use crate::graph::{Graph, View, MAX_DEPTH};
+use crate::stats_source::Relation;
+
+/// Spread and length of the arrowheads, in radians and layers.
+const ARROWHEAD_ANGLE: f64 = 0.4;
+const ARROWHEAD_LENGTH: f64 = 0.12;
+
+pub struct GraphRenderer<'g> {
+    graph: &'g Graph,
+    domains: &'g DomainList,
+    view: &'g View,
+}
+
+impl<'g> GraphRenderer<'g> {
+    /// Draws `graph` as panned by `view`, the domains in scope of `domains`
+    /// colored by prevalence.
+    pub fn new(graph: &'g Graph, domains: &'g DomainList, view: &'g View) -> Self {
+        Self {
+            graph,
+            domains,
+            view,
+        }
+    }
+
+    pub fn render<B: Backend>(&self, screen: &mut Frame<B>, container: Rect) {
+        let panes = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(3)].as_ref())
+            .split(container);
+
+        self.render_canvas(screen, panes[0]);
+        screen.render_widget(self.render_legend_widget(), panes[1]);
+    }
+
+    fn render_canvas<B: Backend>(&self, screen: &mut Frame<B>, container: Rect) {
+        let center = &self.graph.nodes[0].name;
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title(format!(
+                "Graph of {} ({} domains, depth {} of {})",
+                center,
+                self.graph.nodes.len(),
+                self.view.depth,
+                MAX_DEPTH
+            ))
+            .border_type(BorderType::Plain);
+
+        // Cells are about twice as tall as wide, the horizontal bounds are
+        // widened accordingly to keep the layers round.
+        let inner = block.inner(container);
+        let height = self.graph.radius();
+        let width = height * f64::from(inner.width) / (2.0 * f64::from(inner.height.max(1)));
+        let x_bounds = [self.view.x - width, self.view.x + width];
+        let y_bounds = [self.view.y - height, self.view.y + height];
+
+        let canvas = Canvas::default()
+            .block(block)
+            .x_bounds(x_bounds)
+            .y_bounds(y_bounds)
+            .paint(|ctx| {
+                for edge in &self.graph.edges {
+                    let from = &self.graph.nodes[edge.from];
+                    let to = &self.graph.nodes[edge.to];
+                    let color = relation_color(edge.relation);
+
+                    ctx.draw(&Line {
+                        x1: from.x,
+                        y1: from.y,
+                        x2: to.x,
+                        y2: to.y,
+                        color,
+                    });
+
+                    // An arrowhead short of the target, clear of its label.
+                    let angle = (to.y - from.y).atan2(to.x - from.x);
+                    let (tip_x, tip_y) = (to.x - 0.3 * angle.cos(), to.y - 0.3 * angle.sin());
+
+                    for side in [-ARROWHEAD_ANGLE, ARROWHEAD_ANGLE] {
+                        ctx.draw(&Line {
+                            x1: tip_x,
+                            y1: tip_y,
+                            x2: tip_x - ARROWHEAD_LENGTH * (angle + side).cos(),
+                            y2: tip_y - ARROWHEAD_LENGTH * (angle + side).sin(),
+                            color,
+                        });
+                    }
+                }
+            });
+
+        screen.render_widget(canvas, container);
+
+        // The canvas only prints static labels, names are drawn over it
+        // instead, centered on their node.
+        for (index, node) in self.graph.nodes.iter().enumerate() {
+            let column = (node.x - x_bounds[0]) / (x_bounds[1] - x_bounds[0])
+                * f64::from(inner.width.saturating_sub(1));
+            let row = (y_bounds[1] - node.y) / (y_bounds[1] - y_bounds[0])
+                * f64::from(inner.height.saturating_sub(1));
+            let length = node.name.chars().count() as f64;
+            let start = (column - length / 2.0).round();
+
+            if row < 0.0
+                || row >= f64::from(inner.height)
+                || start >= f64::from(inner.width)
+                || start + length <= 0.0
+            {
+                continue;
+            }
+
+            // Names running off the left edge are cut from their start.
+            let skipped = (-start).max(0.0) as usize;
+            let x = inner.x + start.max(0.0) as u16;
+            let area = Rect {
+                x,
+                y: inner.y + row as u16,
+                width: (inner.x + inner.width - x).min((length as usize - skipped) as u16),
+                height: 1,
+            };
+            let color = match index {
+                0 => Color::LightCyan,
+                _ => self.node_color(&node.name),
+            };
+            let name: String = node.name.chars().skip(skipped).collect();
+
+            screen.render_widget(
+                Paragraph::new(Span::styled(name, Style::default().fg(color))),
+                area,
+            );
+        }
+    }
+
+    /// Very prevalent domains in red, prevalent ones in yellow.
+    fn node_color(&self, name: &str) -> Color {
+        match self.domains.scoped(name) {
+            Some(domain) if domain.very_prevalent => Color::Red,
+            Some(domain) if domain.prevalent => Color::Yellow,
+            _ => Color::White,
+        }
+    }
+
+    fn render_legend_widget(&self) -> Paragraph<'g> {
+        let edge = |relation: Relation, label| {
+            vec![
+                Span::styled("── ", Style::default().fg(relation_color(relation))),
+                Span::raw(label),
+            ]
+        };
+
+        let mut spans = Vec::new();
+        spans.extend(edge(Relation::Iframe, "iframed under   "));
+        spans.extend(edge(Relation::Subresource, "loaded under   "));
+        spans.extend(edge(Relation::Redirect, "redirects to   "));
+        spans.push(Span::styled(
+            "very prevalent ",
+            Style::default().fg(Color::Red),
+        ));
+        spans.push(Span::styled(
+            "prevalent   ",
+            Style::default().fg(Color::Yellow),
+        ));
+        spans.push(Span::raw("+/- depth, arrows to pan, 0 to recenter"));
+
+        Paragraph::new(Spans::from(spans))
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::White))
+                    .title("Legend")
+                    .border_type(BorderType::Plain),
+            )
+    }
+}
+
+fn relation_color(relation: Relation) -> Color {
+    match relation {
+        Relation::Iframe => Color::Cyan,
+        Relation::Subresource => Color::Green,
+        Relation::Redirect => Color::Magenta,
+    }
+}
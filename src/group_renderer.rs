@@ -0,0 +1,173 @@
+use tui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, Cell, List, ListItem, Row, Table, TableState},
+    Frame,
+};
+
+use crate::groups::{CrossGroup, GroupStats};
+
+pub struct GroupRenderer<'g> {
+    stats: &'g [GroupStats<'g>],
+    crossings: &'g [CrossGroup<'g>],
+}
+
+impl<'g> GroupRenderer<'g> {
+    pub fn new(stats: &'g [GroupStats<'g>], crossings: &'g [CrossGroup<'g>]) -> Self {
+        Self { stats, crossings }
+    }
+
+    pub fn render<B: Backend>(
+        &self,
+        screen: &mut Frame<B>,
+        container: Rect,
+        table_state: &mut TableState,
+    ) {
+        let panes = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)].as_ref())
+            .split(container);
+
+        let details_panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
+            .split(panes[1]);
+
+        screen.render_stateful_widget(self.render_summary_widget(), panes[0], table_state);
+
+        let selected = table_state
+            .selected()
+            .and_then(|index| self.stats.get(index));
+
+        if let Some(selected) = selected {
+            screen.render_widget(self.render_prevalent_widget(selected), details_panes[0]);
+            screen.render_widget(self.render_crossings_widget(selected), details_panes[1]);
+        }
+    }
+
+    /// Aggregate counts of every group, one per row.
+    fn render_summary_widget(&self) -> Table<'g> {
+        let header_style = Style::default().add_modifier(Modifier::BOLD);
+
+        let rows: Vec<Row> = self
+            .stats
+            .iter()
+            .map(|stats| {
+                Row::new(vec![
+                    Cell::from(Span::raw(stats.group.name.clone())),
+                    Cell::from(Span::raw(stats.members.len().to_string())),
+                    Cell::from(Span::raw(stats.count(|d| d.prevalent).to_string())),
+                    Cell::from(Span::raw(stats.count(|d| d.very_prevalent).to_string())),
+                    Cell::from(Span::raw(
+                        stats.count(|d| d.had_user_interaction).to_string(),
+                    )),
+                    Cell::from(Span::raw(stats.interaction.iframes.to_string())),
+                    Cell::from(Span::raw(stats.interaction.requests.to_string())),
+                    Cell::from(Span::raw(stats.interaction.redirects.to_string())),
+                ])
+            })
+            .collect();
+
+        Table::new(rows)
+            .header(Row::new(vec![
+                Cell::from(Span::styled("GROUP", header_style)),
+                Cell::from(Span::styled("OBSERVED", header_style)),
+                Cell::from(Span::styled("PREVALENT", header_style)),
+                Cell::from(Span::styled("VERY PREVALENT", header_style)),
+                Cell::from(Span::styled("INTERACTED", header_style)),
+                Cell::from(Span::styled("IFRAMED", header_style)),
+                Cell::from(Span::styled("REQUESTS TO", header_style)),
+                Cell::from(Span::styled("REDIRECTS", header_style)),
+            ]))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::White))
+                    .title("Groups")
+                    .border_type(BorderType::Plain),
+            )
+            .highlight_style(
+                Style::default()
+                    .bg(Color::Yellow)
+                    .fg(Color::Black)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .widths(&[
+                Constraint::Percentage(20),
+                Constraint::Percentage(10),
+                Constraint::Percentage(10),
+                Constraint::Percentage(14),
+                Constraint::Percentage(11),
+                Constraint::Percentage(10),
+                Constraint::Percentage(13),
+                Constraint::Percentage(12),
+            ])
+    }
+
+    fn render_prevalent_widget(&self, stats: &GroupStats) -> List<'g> {
+        let items: Vec<ListItem> = stats
+            .prevalent()
+            .map(|domain| {
+                let label = match domain.very_prevalent {
+                    true => format!("{} (very prevalent)", domain.name),
+                    false => domain.name.clone(),
+                };
+                ListItem::new(Spans::from(vec![Span::raw(label)]))
+            })
+            .collect();
+
+        List::new(items).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title("Prevalent members")
+                .border_type(BorderType::Plain),
+        )
+    }
+
+    /// Relationships between the selected group and the other ones, in both
+    /// directions.
+    fn render_crossings_widget(&self, stats: &GroupStats) -> Table<'g> {
+        let header_style = Style::default().add_modifier(Modifier::BOLD);
+
+        let rows: Vec<Row> = self
+            .crossings
+            .iter()
+            .filter(|c| std::ptr::eq(c.from, stats.group) || std::ptr::eq(c.to, stats.group))
+            .map(|crossing| {
+                Row::new(vec![
+                    Cell::from(Span::raw(crossing.from.name.clone())),
+                    Cell::from(Span::raw(crossing.relationship.from.clone())),
+                    Cell::from(Span::raw(crossing.relationship.relation.verb().to_owned())),
+                    Cell::from(Span::raw(crossing.relationship.to.clone())),
+                    Cell::from(Span::raw(crossing.to.name.clone())),
+                ])
+            })
+            .collect();
+
+        Table::new(rows)
+            .header(Row::new(vec![
+                Cell::from(Span::styled("GROUP", header_style)),
+                Cell::from(Span::styled("DOMAIN", header_style)),
+                Cell::from(Span::styled("", header_style)),
+                Cell::from(Span::styled("DOMAIN", header_style)),
+                Cell::from(Span::styled("GROUP", header_style)),
+            ]))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::White))
+                    .title("Cross-group relationships")
+                    .border_type(BorderType::Plain),
+            )
+            .widths(&[
+                Constraint::Percentage(18),
+                Constraint::Percentage(22),
+                Constraint::Percentage(16),
+                Constraint::Percentage(22),
+                Constraint::Percentage(18),
+            ])
+    }
+}
@@ -0,0 +1,155 @@
+use serde::Deserialize;
+
+use crate::database::{Check, Domain, DomainInteraction};
+use crate::scope::{self, Scope};
+use crate::stats_source::{Relationship, StatsSource};
+
+/// A named set of domains as configured in `.itprc`:
+///
+/// ```toml
+/// [[group]]
+/// name = "Ad partners"
+/// domains = ["ads.example", "*.doubleclick.net"]
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct GroupConfig {
+    pub name: String,
+    #[serde(default)]
+    pub domains: Vec<String>,
+}
+
+/// A group whose domains are matched the same way scoped domains are.
+pub struct Group {
+    pub name: String,
+    scope: Scope,
+}
+
+impl Group {
+    /// Builds the configured groups, along with a warning for every domain
+    /// that was normalized. Groups made of exclusions only would hold every
+    /// domain, they are refused along with empty ones.
+    pub fn parse(configs: &[GroupConfig]) -> Result<(Vec<Group>, Vec<String>), String> {
+        let mut groups = Vec::new();
+        let mut warnings = Vec::new();
+
+        for config in configs {
+            if config.domains.iter().all(|domain| domain.starts_with('!')) {
+                return Err(format!("group {} has no domains", config.name));
+            }
+
+            let (domains, normalized) = scope::normalize(config.domains.clone());
+
+            groups.push(Group {
+                name: config.name.clone(),
+                scope: Scope::parse(&domains)
+                    .map_err(|e| format!("group {} has an invalid pattern: {}", config.name, e))?,
+            });
+            warnings.extend(normalized);
+        }
+
+        Ok((groups, warnings))
+    }
+
+    pub fn contains(&self, domain: &str) -> bool {
+        self.scope.matches(domain)
+    }
+}
+
+/// Aggregated statistics of the observed members of a group.
+pub struct GroupStats<'g> {
+    pub group: &'g Group,
+    pub members: Vec<Domain>,
+    pub interaction: DomainInteraction,
+}
+
+impl<'g> GroupStats<'g> {
+    pub fn compute(group: &'g Group, db: &dyn StatsSource) -> Self {
+        let observed = db.get_domains().unwrap_or_default();
+        let mut interaction = DomainInteraction::default();
+
+        let members: Vec<Domain> = group
+            .scope
+            .select(observed)
+            .into_iter()
+            .filter(|domain| domain.id != 0)
+            .filter_map(|domain| {
+                let counts = db.domain_interaction(&domain);
+                interaction.iframes += counts.iframes;
+                interaction.requests += counts.requests;
+                interaction.redirects += counts.redirects;
+
+                db.get_info(&domain).ok()
+            })
+            .collect();
+
+        GroupStats {
+            group,
+            members,
+            interaction,
+        }
+    }
+
+    pub fn prevalent(&self) -> impl Iterator<Item = &Domain> {
+        self.members.iter().filter(|domain| domain.prevalent)
+    }
+
+    pub fn count(&self, check: Check) -> usize {
+        self.members.iter().filter(|domain| check(domain)).count()
+    }
+}
+
+/// A relationship between members of two different groups.
+pub struct CrossGroup<'g> {
+    pub from: &'g Group,
+    pub relationship: Relationship,
+    pub to: &'g Group,
+}
+
+/// Every relationship crossing from a group to another, such as partners
+/// iframed under our properties.
+pub fn cross_group<'g>(groups: &'g [Group], relationships: &[Relationship]) -> Vec<CrossGroup<'g>> {
+    let mut crossings = Vec::new();
+
+    for relationship in relationships {
+        for from in groups.iter().filter(|g| g.contains(&relationship.from)) {
+            for to in groups.iter().filter(|g| g.contains(&relationship.to)) {
+                if !std::ptr::eq(from, to) {
+                    crossings.push(CrossGroup {
+                        from,
+                        relationship: relationship.clone(),
+                        to,
+                    });
+                }
+            }
+        }
+    }
+
+    crossings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(domains: &[&str]) -> GroupConfig {
+        GroupConfig {
+            name: "Partners".to_owned(),
+            domains: domains.iter().map(|domain| domain.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn refuses_groups_holding_every_domain() {
+        for domains in [&[][..], &["!ads.example"][..]] {
+            assert_eq!(
+                Group::parse(&[config(domains)]).err(),
+                Some("group Partners has no domains".to_owned())
+            );
+        }
+
+        let (groups, _) = Group::parse(&[config(&["*.example", "!ads.example"])]).unwrap();
+        assert!(groups[0].contains("shop.example"));
+        assert!(!groups[0].contains("ads.example"));
+        assert!(!groups[0].contains("shop.test"));
+    }
+}
@@ -1,9 +1,14 @@
+use std::path::PathBuf;
+
 use tui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans},
-    widgets::{Block, BorderType, Borders, Paragraph, Tabs},
+    widgets::{Block, BorderType, Borders, List, ListItem, Paragraph, Tabs},
 };
+
+use crate::discovery::Candidate;
+use crate::search::{Input, Search, CHIPS};
 pub struct Gui;
 
 impl<'a> Gui {
@@ -22,12 +27,27 @@ impl<'a> Gui {
             .split(screen_size)
     }
 
-    pub fn render_tabs(titles: &'a Vec<&'a str>, selected_item_index: usize) -> Tabs {
+    /// The search bar above the Domains tab.
+    pub fn render_domain_panes(container: Rect) -> Vec<Rect> {
+        Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+            .split(container)
+    }
+
+    /// Tabs along with the key opening them, underlined in their title.
+    pub fn render_tabs(titles: &'a [(&'a str, char)], selected_item_index: usize) -> Tabs<'a> {
         let menu = titles
             .iter()
-            .map(|t| {
-                let (first, rest) = t.split_at(1);
-                Spans::from(Gui::render_menu_style(first, rest))
+            .map(|(title, key)| {
+                let position = title
+                    .char_indices()
+                    .find(|(_, c)| c.eq_ignore_ascii_case(key))
+                    .map_or(0, |(position, _)| position);
+                let (before, rest) = title.split_at(position);
+                let (key, after) = rest.split_at(rest.chars().next().map_or(0, char::len_utf8));
+
+                Spans::from(Gui::render_menu_style(before, key, after))
             })
             .collect();
 
@@ -52,6 +72,19 @@ impl<'a> Gui {
             )
     }
 
+    pub fn render_status(message: &'a str) -> Paragraph<'a> {
+        Paragraph::new(message)
+            .style(Style::default().fg(Color::Yellow))
+            .alignment(Alignment::Center)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::White))
+                    .title("Status")
+                    .border_type(BorderType::Plain),
+            )
+    }
+
     pub fn render_home_pane() -> Paragraph<'a> {
         let home = Paragraph::new(vec![
             Spans::from(vec![Span::raw("")]),
@@ -65,9 +98,29 @@ impl<'a> Gui {
             )]),
             Spans::from(vec![Span::raw("")]),
             Spans::from(vec![Span::raw(
-                "Press 'h' for Home, 'd' for Domains or 'q' to quit.",
+                "Press 'h' for Home, 'd' for Domains, 's' for Simulators, 'p' for Profiles, 'g' for Groups, 'a' for Graph or 'q' to quit.",
+            )]),
+            Spans::from(vec![Span::raw(
+                "Navigate through domains with arrow keys, press 'e' to group them by entity.",
+            )]),
+            Spans::from(vec![Span::raw(
+                "Press 'o' to change the column domains are sorted by, 'r' to reverse it and 'c' to hide the columns.",
+            )]),
+            Spans::from(vec![Span::raw(
+                "Press 'u' to reload the database along with the cookies and website data.",
+            )]),
+            Spans::from(vec![Span::raw(
+                "Press '/' to search domains, 'n'/'N' for the next or previous match, 'f' to filter them and '1' to '4' to toggle filter chips.",
+            )]),
+            Spans::from(vec![Span::raw(
+                "Press Tab to pick a related domain, Enter to jump to it and Backspace to go back.",
+            )]),
+            Spans::from(vec![Span::raw(
+                "Press 'a' to draw the selected domain's relationships, '+'/'-' to change their depth and arrows to pan.",
+            )]),
+            Spans::from(vec![Span::raw(
+                "In lab mode press 'i' to toggle user interaction and 't' to toggle prevalence.",
             )]),
-            Spans::from(vec![Span::raw("Navigate through domains with arrow keys.")]),
         ])
         .alignment(Alignment::Center)
         .block(
@@ -80,10 +133,120 @@ impl<'a> Gui {
         home
     }
 
-    fn render_menu_style(first: &'a str, rest: &'a str) -> Vec<Span<'a>> {
+    /// Databases to choose from, the one currently open is marked.
+    pub fn render_candidates(
+        title: &'a str,
+        candidates: &[Candidate],
+        current: Option<&PathBuf>,
+    ) -> List<'a> {
+        let items: Vec<_> = candidates
+            .iter()
+            .map(|candidate| {
+                let marker = match Some(&candidate.path) == current {
+                    true => "● ",
+                    false => "  ",
+                };
+
+                ListItem::new(vec![
+                    Spans::from(vec![
+                        Span::styled(marker, Style::default().fg(Color::LightGreen)),
+                        Span::raw(candidate.label.clone()),
+                    ]),
+                    Spans::from(vec![Span::styled(
+                        format!("  {}", candidate.path.display()),
+                        Style::default().fg(Color::DarkGray),
+                    )]),
+                ])
+            })
+            .collect();
+
+        List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .style(Style::default().fg(Color::White))
+                    .title(title)
+                    .border_type(BorderType::Plain),
+            )
+            .highlight_style(
+                Style::default()
+                    .bg(Color::Yellow)
+                    .fg(Color::Black)
+                    .add_modifier(Modifier::BOLD),
+            )
+    }
+
+    /// Query typed after `/`, filter expression typed after `f` and the
+    /// filter chips toggled with the number keys.
+    pub fn render_search_bar(search: &'a Search) -> Paragraph<'a> {
+        let cursor = |input| {
+            Span::styled(
+                if search.input == Some(input) { "_" } else { "" },
+                Style::default().add_modifier(Modifier::SLOW_BLINK),
+            )
+        };
+        let mut spans = Vec::new();
+
+        if search.input == Some(Input::Query) || !search.query.is_empty() {
+            spans.push(Span::styled("/", Style::default().fg(Color::Yellow)));
+            spans.push(Span::raw(search.query.as_str()));
+            spans.push(cursor(Input::Query));
+            spans.push(Span::raw("  "));
+        }
+        if search.input == Some(Input::Filter) || search.filter.is_some() {
+            spans.push(Span::styled("where ", Style::default().fg(Color::Yellow)));
+            spans.push(Span::raw(search.expression.as_str()));
+            spans.push(cursor(Input::Filter));
+            spans.push(Span::raw("  "));
+        }
+        if spans.is_empty() {
+            spans.push(Span::styled(
+                "Press / to search, f to filter",
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+
+        for (index, (label, _)) in CHIPS.iter().enumerate() {
+            let style = match search.is_active(index) {
+                true => Style::default().fg(Color::Black).bg(Color::Yellow),
+                false => Style::default().fg(Color::DarkGray),
+            };
+
+            spans.push(Span::raw("  "));
+            spans.push(Span::styled(format!(" {} {} ", index + 1, label), style));
+        }
+
+        Paragraph::new(Spans::from(spans)).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title("Search")
+                .border_type(BorderType::Plain),
+        )
+    }
+
+    pub fn render_empty_pane(title: &'a str, message: &'a str, hint: &'a str) -> Paragraph<'a> {
+        Paragraph::new(vec![
+            Spans::from(vec![Span::raw("")]),
+            Spans::from(vec![Span::styled(message, Style::default().fg(Color::Red))]),
+            Spans::from(vec![Span::raw("")]),
+            Spans::from(vec![Span::raw(hint)]),
+        ])
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title(title)
+                .border_type(BorderType::Plain),
+        )
+    }
+
+    fn render_menu_style(before: &'a str, key: &'a str, rest: &'a str) -> Vec<Span<'a>> {
         vec![
+            Span::styled(before, Style::default().fg(Color::White)),
             Span::styled(
-                first,
+                key,
                 Style::default()
                     .fg(Color::Yellow)
                     .add_modifier(Modifier::UNDERLINED),
@@ -0,0 +1,266 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use rusqlite::{params, Connection, OpenFlags, NO_PARAMS};
+use serde::Deserialize;
+
+use crate::database;
+use crate::discovery::{self, Candidate};
+
+const MANIFEST_DATABASE: &str = "Manifest.db";
+const MANIFEST_PLIST: &str = "Manifest.plist";
+
+/// Every ITP database in the backup, Safari's or any app embedding WebKit.
+const ITP_FILES: &str = "SELECT fileID, domain, relativePath FROM Files WHERE flags = 1 AND relativePath LIKE '%ResourceLoadStatistics/observations.db'";
+const WAL_FILE: &str =
+    "SELECT fileID FROM Files WHERE flags = 1 AND domain = ? AND relativePath = ? || '-wal'";
+
+const SAFARI_DOMAIN: &str = "AppDomain-com.apple.mobilesafari";
+const SAFARI_BUNDLE_ID: &str = "com.apple.mobilesafari";
+
+#[derive(Debug)]
+pub enum BackupError {
+    Encrypted,
+    NotFound(PathBuf),
+    /// A file id of `Manifest.db` that can't name a stored file.
+    InvalidFileId(String),
+    Database(rusqlite::Error),
+    Io(io::Error),
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupError::Encrypted => write!(f, "encrypted backups are not supported"),
+            BackupError::NotFound(path) => {
+                write!(f, "no ITP database found in backup {}", path.display())
+            }
+            BackupError::InvalidFileId(id) => write!(f, "invalid file id {:?} in Manifest.db", id),
+            BackupError::Database(e) => write!(f, "database error: {}", e),
+            BackupError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+impl From<rusqlite::Error> for BackupError {
+    fn from(e: rusqlite::Error) -> Self {
+        BackupError::Database(e)
+    }
+}
+
+impl From<io::Error> for BackupError {
+    fn from(e: io::Error) -> Self {
+        BackupError::Io(e)
+    }
+}
+
+#[derive(Deserialize)]
+struct Manifest {
+    #[serde(rename = "IsEncrypted", default)]
+    is_encrypted: bool,
+}
+
+/// A file listed in the backup's `Manifest.db`.
+struct BackupFile {
+    id: String,
+    domain: String,
+    relative_path: String,
+}
+
+impl BackupFile {
+    /// Safari's database lives either in its own app domain or, on older iOS
+    /// versions, in a WebKit directory named after it in the home domain.
+    fn is_safari(&self) -> bool {
+        match self.domain.as_str() {
+            "HomeDomain" => self.relative_path.contains(SAFARI_BUNDLE_ID),
+            domain => domain == SAFARI_DOMAIN,
+        }
+    }
+}
+
+/// Copies the ITP database out of an unencrypted iOS backup into a temporary
+/// file, leaving the backup untouched.
+///
+/// Safari's database is preferred, otherwise the user picks among the apps
+/// embedding WebKit found in the backup.
+pub fn extract(backup: &Path) -> Result<PathBuf, BackupError> {
+    if is_encrypted(backup) {
+        return Err(BackupError::Encrypted);
+    }
+
+    let manifest = Connection::open_with_flags(
+        backup.join(MANIFEST_DATABASE),
+        OpenFlags::SQLITE_OPEN_READ_ONLY,
+    )?;
+
+    let files: Vec<BackupFile> = {
+        let mut stmt = manifest.prepare(ITP_FILES)?;
+        let rows = stmt.query_map(NO_PARAMS, |row| {
+            Ok(BackupFile {
+                id: row.get(0)?,
+                domain: row.get(1)?,
+                relative_path: row.get(2)?,
+            })
+        })?;
+        rows.filter_map(|r| r.ok()).collect()
+    };
+
+    let file = match files.iter().find(|file| file.is_safari()) {
+        Some(file) => file,
+        None => {
+            let candidates: Vec<Candidate> = files
+                .iter()
+                .map(|file| Candidate {
+                    label: file.domain.clone(),
+                    path: PathBuf::from(&file.relative_path),
+                })
+                .collect();
+
+            // A domain and relative path name a single file of the backup.
+            let picked = discovery::pick(&candidates).and_then(|picked| {
+                candidates
+                    .iter()
+                    .position(|c| c.label == picked.label && c.path == picked.path)
+            });
+
+            match picked {
+                Some(index) => &files[index],
+                None => return Err(BackupError::NotFound(backup.to_path_buf())),
+            }
+        }
+    };
+
+    let destination =
+        std::env::temp_dir().join(format!("itp_tldr-ios-backup-{}.db", process::id()));
+    database::remove(&destination);
+    fs::copy(stored_path(backup, &file.id)?, &destination)?;
+
+    // Changes not yet checkpointed by the device are only in the WAL file.
+    let wal_id: Option<String> = manifest
+        .query_row(WAL_FILE, params![file.domain, file.relative_path], |row| {
+            row.get(0)
+        })
+        .ok();
+
+    if let Some(wal_id) = wal_id {
+        let mut wal = destination.clone().into_os_string();
+        wal.push("-wal");
+        fs::copy(stored_path(backup, &wal_id)?, wal)?;
+    }
+
+    Ok(destination)
+}
+
+fn is_encrypted(backup: &Path) -> bool {
+    plist::from_file::<_, Manifest>(backup.join(MANIFEST_PLIST))
+        .map(|manifest| manifest.is_encrypted)
+        .unwrap_or(false)
+}
+
+/// Backups store each file under a directory named after the first two
+/// characters of its id, a hexadecimal hash.
+fn stored_path(backup: &Path, id: &str) -> Result<PathBuf, BackupError> {
+    match id.get(..2) {
+        Some(directory) if id.len() > 2 && id.chars().all(|c| c.is_ascii_hexdigit()) => {
+            Ok(backup.join(directory).join(id))
+        }
+        _ => Err(BackupError::InvalidFileId(id.to_owned())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{Database, TemporaryDatabase};
+    use crate::fixture;
+    use crate::scenario::Scenario;
+    use crate::stats_source::StatsSource;
+    use crate::Config;
+
+    const SAFARI_DATABASE: &str =
+        "Library/WebKit/WebsiteData/ResourceLoadStatistics/observations.db";
+    const SAFARI_FILE_ID: &str = "3d0d7e5fb2ce288813306e4d4636395e047a3d28";
+
+    /// A backup holding Safari's database, generated out of the tracker scenario.
+    fn backup(name: &str, encrypted: bool) -> PathBuf {
+        let backup = fixture::temporary_path(name);
+        let _ = fs::remove_dir_all(&backup);
+        fs::create_dir_all(backup.join(&SAFARI_FILE_ID[..2])).unwrap();
+
+        fs::write(
+            backup.join(MANIFEST_PLIST),
+            format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0"><dict><key>IsEncrypted</key><{}/></dict></plist>"#,
+                encrypted
+            ),
+        )
+        .unwrap();
+
+        let manifest = Connection::open(backup.join(MANIFEST_DATABASE)).unwrap();
+        manifest
+            .execute_batch(
+                "CREATE TABLE Files (fileID TEXT PRIMARY KEY, domain TEXT, relativePath TEXT, flags INTEGER, file BLOB)",
+            )
+            .unwrap();
+        manifest
+            .execute(
+                "INSERT INTO Files (fileID, domain, relativePath, flags) VALUES (?, ?, ?, 1)",
+                params![SAFARI_FILE_ID, SAFARI_DOMAIN, SAFARI_DATABASE],
+            )
+            .unwrap();
+
+        let scenario = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios/tracker.toml");
+        Scenario::from_file(&scenario)
+            .unwrap()
+            .generate(&stored_path(&backup, SAFARI_FILE_ID).unwrap())
+            .unwrap();
+
+        backup
+    }
+
+    #[test]
+    fn extracts_safari_database() {
+        let backup = backup("backup", false);
+        let extracted = TemporaryDatabase::new(extract(&backup).unwrap());
+
+        let db = Database::connect(Config {
+            path: Some(extracted.path().to_owned()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!(db.find("tracker.example").is_some());
+
+        fs::remove_dir_all(backup).unwrap();
+    }
+
+    #[test]
+    fn refuses_encrypted_backups() {
+        let backup = backup("encrypted-backup", true);
+
+        assert!(matches!(extract(&backup), Err(BackupError::Encrypted)));
+
+        fs::remove_dir_all(backup).unwrap();
+    }
+
+    #[test]
+    fn rejects_malformed_file_ids() {
+        let backup = Path::new("backup");
+
+        for id in ["", "3", "é1", "3é", "../Manifest.db"].iter() {
+            assert!(matches!(
+                stored_path(backup, id),
+                Err(BackupError::InvalidFileId(_))
+            ));
+        }
+        assert_eq!(
+            stored_path(backup, SAFARI_FILE_ID).unwrap(),
+            backup.join("3d").join(SAFARI_FILE_ID)
+        );
+    }
+}
@@ -0,0 +1,464 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension, Transaction, NO_PARAMS};
+
+use crate::clock;
+use crate::database;
+
+const DOMAIN_ID: &str = "SELECT domainID FROM ObservedDomains WHERE registrableDomain = ?";
+const OBSERVED_COLUMNS: &str = "PRAGMA table_info(ObservedDomains)";
+const SET_INTERACTION: &str = "UPDATE ObservedDomains SET hadUserInteraction = ?, mostRecentUserInteractionTime = ? WHERE domainID = ?";
+const SET_PREVALENCE: &str =
+    "UPDATE ObservedDomains SET isPrevalent = ?, isVeryPrevalent = ? WHERE domainID = ?";
+const ADD_TOP_LEVEL: &str = "INSERT OR IGNORE INTO TopLevelDomains (topLevelDomainID) VALUES (?)";
+const ADD_SUBFRAME: &str = "INSERT INTO SubframeUnderTopFrameDomains (subFrameDomainID, topFrameDomainID) SELECT ?1, ?2 WHERE NOT EXISTS (SELECT 1 FROM SubframeUnderTopFrameDomains WHERE subFrameDomainID = ?1 AND topFrameDomainID = ?2)";
+const ADD_REDIRECT_TO: &str = "INSERT INTO TopFrameUniqueRedirectsTo (sourceDomainID, toDomainID) SELECT ?1, ?2 WHERE NOT EXISTS (SELECT 1 FROM TopFrameUniqueRedirectsTo WHERE sourceDomainID = ?1 AND toDomainID = ?2)";
+const ADD_REDIRECT_FROM: &str = "INSERT INTO TopFrameUniqueRedirectsFrom (targetDomainID, fromDomainID) SELECT ?1, ?2 WHERE NOT EXISTS (SELECT 1 FROM TopFrameUniqueRedirectsFrom WHERE targetDomainID = ?1 AND fromDomainID = ?2)";
+
+/// Processes that keep a database open and would overwrite our edits, by the
+/// directory the browser keeps its data in: Safari, WebKitGTK browsers,
+/// Chromium based browsers and Firefox. Directories are compared regardless
+/// of their case.
+const BROWSER_PROCESSES: &[(&str, &[&str])] = &[
+    ("com.apple.Safari", &["Safari"]),
+    (
+        "com.apple.SafariTechnologyPreview",
+        &["Safari Technology Preview"],
+    ),
+    ("com.apple.mobilesafari", &["MobileSafari"]),
+    ("epiphany", &["epiphany"]),
+    ("Chrome", &["Google Chrome", "chrome"]),
+    ("google-chrome", &["Google Chrome", "chrome"]),
+    ("Chromium", &["Chromium", "chromium"]),
+    ("Microsoft Edge", &["Microsoft Edge", "msedge"]),
+    ("microsoft-edge", &["Microsoft Edge", "msedge"]),
+    ("Brave-Browser", &["Brave Browser", "brave"]),
+    ("firefox", &["firefox"]),
+];
+
+const BACKUP_EXTENSION: &str = "itp_tldr-backup";
+const WORKING_COPY_EXTENSION: &str = "itp_tldr-lab";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Prevalence {
+    None,
+    Prevalent,
+    VeryPrevalent,
+}
+
+/// A single change to ITP's state, domains are created when missing.
+#[derive(Debug, Clone)]
+pub enum Edit {
+    /// Records a user interaction at the given time, `None` forgets it.
+    Interaction(String, Option<f64>),
+    Prevalence(String, Prevalence),
+    Subframe {
+        subframe: String,
+        top_frame: String,
+    },
+    Redirect {
+        from: String,
+        to: String,
+    },
+}
+
+#[derive(Debug)]
+pub enum LabError {
+    BrowserRunning(String),
+    /// Running browsers couldn't be listed, editing is refused rather than risked.
+    CannotCheck(String),
+    NoBackup(PathBuf),
+    /// SQLite only takes UTF-8 paths.
+    InvalidPath(PathBuf),
+    Database(rusqlite::Error),
+    Io(io::Error),
+}
+
+impl fmt::Display for LabError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LabError::BrowserRunning(name) => {
+                write!(
+                    f,
+                    "{} is running, quit it before editing its database",
+                    name
+                )
+            }
+            LabError::CannotCheck(reason) => {
+                write!(f, "couldn't check whether a browser is running: {}", reason)
+            }
+            LabError::NoBackup(path) => write!(f, "no backup found at {}", path.display()),
+            LabError::InvalidPath(path) => write!(f, "{} isn't valid UTF-8", path.display()),
+            LabError::Database(e) => write!(f, "database error: {}", e),
+            LabError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LabError {}
+
+impl From<rusqlite::Error> for LabError {
+    fn from(e: rusqlite::Error) -> Self {
+        LabError::Database(e)
+    }
+}
+
+impl From<io::Error> for LabError {
+    fn from(e: io::Error) -> Self {
+        LabError::Io(e)
+    }
+}
+
+/// Applies `edits` to a copy of the database at `path` and swaps it in place.
+///
+/// The original database is backed up before the first edit, further edits keep
+/// that backup so `restore` always goes back to the state before the lab session.
+///
+/// Edits failing partway leave the database as it was, without a working copy
+/// or a backup taken for nothing.
+pub fn apply(path: &Path, edits: &[Edit]) -> Result<(), LabError> {
+    ensure_browser_closed(path)?;
+
+    let backup = sibling(path, BACKUP_EXTENSION);
+    let backed_up = backup.exists();
+    if !backed_up {
+        copy_database(path, &backup)?;
+    }
+
+    let working_copy = sibling(path, WORKING_COPY_EXTENSION);
+    if working_copy.exists() {
+        fs::remove_file(&working_copy)?;
+    }
+
+    if let Err(e) = copy_database(path, &working_copy).and_then(|()| edit(&working_copy, edits)) {
+        database::remove(&working_copy);
+        if !backed_up {
+            fs::remove_file(&backup)?;
+        }
+        return Err(e);
+    }
+
+    swap(&working_copy, path)
+}
+
+/// Puts the backup taken by `apply` back in place.
+pub fn restore(path: &Path) -> Result<(), LabError> {
+    ensure_browser_closed(path)?;
+
+    let backup = sibling(path, BACKUP_EXTENSION);
+    if !backup.exists() {
+        return Err(LabError::NoBackup(backup));
+    }
+
+    let working_copy = sibling(path, WORKING_COPY_EXTENSION);
+    fs::copy(&backup, &working_copy)?;
+    swap(&working_copy, path)?;
+    fs::remove_file(backup)?;
+
+    Ok(())
+}
+
+/// Applies every edit to the database at `path`, or none of them.
+fn edit(path: &Path, edits: &[Edit]) -> Result<(), LabError> {
+    let mut connection = Connection::open(path)?;
+    let transaction = connection.transaction()?;
+
+    for edit in edits {
+        apply_edit(&transaction, edit)?;
+    }
+
+    Ok(transaction.commit()?)
+}
+
+fn apply_edit(transaction: &Transaction, edit: &Edit) -> Result<(), LabError> {
+    match edit {
+        Edit::Interaction(domain, at) => {
+            let id = domain_id(transaction, domain)?;
+            let (interacted, time) = match at {
+                Some(time) => (true, *time),
+                None => (false, 0.0),
+            };
+            transaction.execute(SET_INTERACTION, params![interacted, time, id])?;
+        }
+        Edit::Prevalence(domain, prevalence) => {
+            let id = domain_id(transaction, domain)?;
+            let prevalent = *prevalence != Prevalence::None;
+            let very_prevalent = *prevalence == Prevalence::VeryPrevalent;
+            transaction.execute(SET_PREVALENCE, params![prevalent, very_prevalent, id])?;
+        }
+        Edit::Subframe {
+            subframe,
+            top_frame,
+        } => {
+            let subframe_id = domain_id(transaction, subframe)?;
+            let top_frame_id = domain_id(transaction, top_frame)?;
+            transaction.execute(ADD_TOP_LEVEL, params![top_frame_id])?;
+            transaction.execute(ADD_SUBFRAME, params![subframe_id, top_frame_id])?;
+        }
+        Edit::Redirect { from, to } => {
+            let from_id = domain_id(transaction, from)?;
+            let to_id = domain_id(transaction, to)?;
+            transaction.execute(ADD_TOP_LEVEL, params![from_id])?;
+            transaction.execute(ADD_TOP_LEVEL, params![to_id])?;
+            transaction.execute(ADD_REDIRECT_TO, params![from_id, to_id])?;
+            transaction.execute(ADD_REDIRECT_FROM, params![to_id, from_id])?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Id of `domain`, inserting it as a freshly seen domain when unknown.
+///
+/// The columns of `ObservedDomains` vary between WebKit versions, so every
+/// mandatory column besides the name and last seen time is zeroed.
+fn domain_id(transaction: &Transaction, domain: &str) -> rusqlite::Result<i64> {
+    if let Some(id) = transaction
+        .query_row(DOMAIN_ID, params![domain], |row| row.get(0))
+        .optional()?
+    {
+        return Ok(id);
+    }
+
+    let columns: Vec<String> = {
+        let mut stmt = transaction.prepare(OBSERVED_COLUMNS)?;
+        let rows = stmt.query_map(NO_PARAMS, |row| {
+            Ok((row.get::<_, String>(1)?, row.get::<_, bool>(3)?))
+        })?;
+        rows.filter_map(|r| r.ok())
+            .filter(|(name, not_null)| *not_null && name != "domainID")
+            .map(|(name, _)| name)
+            .collect()
+    };
+
+    let values: Vec<String> = columns
+        .iter()
+        .map(|column| match column.as_str() {
+            "registrableDomain" => "?1".to_owned(),
+            "lastSeen" => "?2".to_owned(),
+            _ => "0".to_owned(),
+        })
+        .collect();
+
+    transaction.execute(
+        &format!(
+            "INSERT INTO ObservedDomains ({}) VALUES ({})",
+            columns.join(", "),
+            values.join(", ")
+        ),
+        params![domain, clock::now()],
+    )?;
+
+    Ok(transaction.last_insert_rowid())
+}
+
+/// Processes of the browser owning the database at `path`, none for databases
+/// outside of any browser's directory such as snapshots.
+fn browser_processes(path: &Path) -> Vec<&'static str> {
+    BROWSER_PROCESSES
+        .iter()
+        .filter(|(directory, _)| {
+            path.iter()
+                .any(|component| component.to_string_lossy().eq_ignore_ascii_case(directory))
+        })
+        .flat_map(|(_, processes)| processes.iter().copied())
+        .collect()
+}
+
+/// Fails unless the browser owning the database at `path` is known to be
+/// closed, `pgrep` exiting with 1 when no process matches and with 2 or more
+/// when it couldn't tell.
+fn ensure_browser_closed(path: &Path) -> Result<(), LabError> {
+    for name in browser_processes(path) {
+        let status = Command::new("pgrep")
+            .arg("-x")
+            .arg(name)
+            .output()
+            .map_err(|e| LabError::CannotCheck(format!("pgrep: {}", e)))?
+            .status;
+
+        match status.code() {
+            Some(0) => return Err(LabError::BrowserRunning(name.to_string())),
+            Some(1) => {}
+            _ => return Err(LabError::CannotCheck(format!("pgrep {}", status))),
+        }
+    }
+
+    Ok(())
+}
+
+/// Consistent copy of a database, including anything still in its WAL file.
+fn copy_database(from: &Path, to: &Path) -> Result<(), LabError> {
+    let to_path = to
+        .to_str()
+        .ok_or_else(|| LabError::InvalidPath(to.to_owned()))?;
+    let connection = Connection::open_with_flags(from, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    connection.execute("VACUUM INTO ?", params![to_path])?;
+
+    Ok(())
+}
+
+/// Atomically replaces `path` with `replacement`.
+///
+/// The WAL and shared memory files belong to the replaced database, keeping them
+/// around would make SQLite replay stale pages on top of the new one. The WAL is
+/// checkpointed and emptied first, so that stopping between the rename and the
+/// removal leaves nothing to replay, while the database is never missing.
+fn swap(replacement: &Path, path: &Path) -> Result<(), LabError> {
+    let journals = [
+        database::journal(path, "-wal"),
+        database::journal(path, "-shm"),
+    ];
+
+    if journals[0].exists() {
+        Connection::open(path)?
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", NO_PARAMS, |_| Ok(()))?;
+    }
+
+    fs::rename(replacement, path)?;
+
+    for journal in journals.iter().filter(|journal| journal.exists()) {
+        fs::remove_file(journal)?;
+    }
+
+    Ok(())
+}
+
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    database::journal(path, &format!(".{}", extension))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{Database, Domain, TemporaryDatabase};
+    use crate::fixture;
+    use crate::scenario::Scenario;
+    use crate::stats_source::StatsSource;
+    use crate::Config;
+
+    /// A database where tracker.example is iframed under news.example.
+    fn generate(name: &str) -> TemporaryDatabase {
+        let scenario: Scenario = toml::from_str(
+            r#"
+            [[domain]]
+            name = "tracker.example"
+            iframed_under = ["news.example"]
+            "#,
+        )
+        .unwrap();
+        let path = TemporaryDatabase::new(fixture::temporary_path(name));
+        scenario.generate(path.path()).unwrap();
+        path
+    }
+
+    /// Details of `name` as currently stored at `path`.
+    fn find(path: &Path, name: &str) -> Option<Domain> {
+        let db = Database::connect(Config {
+            path: Some(path.to_owned()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        db.find(name).map(|domain| db.get_info(&domain).unwrap())
+    }
+
+    #[test]
+    fn restores_the_database_as_it_was_before_the_first_edit() {
+        let path = generate("lab-round-trip.db");
+        let backup = sibling(path.path(), BACKUP_EXTENSION);
+
+        apply(
+            path.path(),
+            &[
+                Edit::Prevalence("tracker.example".to_owned(), Prevalence::VeryPrevalent),
+                Edit::Interaction("news.example".to_owned(), Some(1609459200.0)),
+            ],
+        )
+        .unwrap();
+        apply(
+            path.path(),
+            &[Edit::Redirect {
+                from: "bounce.example".to_owned(),
+                to: "tracker.example".to_owned(),
+            }],
+        )
+        .unwrap();
+
+        let tracker = find(path.path(), "tracker.example").unwrap();
+        assert!(tracker.prevalent && tracker.very_prevalent);
+        assert_eq!(
+            find(path.path(), "news.example").unwrap().last_interaction,
+            Some(1609459200.0)
+        );
+        assert!(find(path.path(), "bounce.example").is_some());
+        assert!(backup.exists());
+        assert!(!sibling(path.path(), WORKING_COPY_EXTENSION).exists());
+
+        restore(path.path()).unwrap();
+
+        let tracker = find(path.path(), "tracker.example").unwrap();
+        assert!(!tracker.prevalent && !tracker.very_prevalent);
+        assert!(
+            !find(path.path(), "news.example")
+                .unwrap()
+                .had_user_interaction
+        );
+        assert!(find(path.path(), "bounce.example").is_none());
+        assert!(!backup.exists());
+    }
+
+    #[test]
+    fn leaves_the_database_untouched_when_an_edit_fails() {
+        let path = generate("lab-partial.db");
+        Connection::open(path.path())
+            .unwrap()
+            .execute_batch("DROP TABLE TopFrameUniqueRedirectsFrom")
+            .unwrap();
+
+        let result = apply(
+            path.path(),
+            &[
+                Edit::Prevalence("tracker.example".to_owned(), Prevalence::Prevalent),
+                Edit::Redirect {
+                    from: "news.example".to_owned(),
+                    to: "tracker.example".to_owned(),
+                },
+            ],
+        );
+
+        assert!(matches!(result, Err(LabError::Database(_))));
+        assert!(!find(path.path(), "tracker.example").unwrap().prevalent);
+        assert!(!sibling(path.path(), WORKING_COPY_EXTENSION).exists());
+        assert!(!sibling(path.path(), BACKUP_EXTENSION).exists());
+    }
+
+    #[test]
+    fn refuses_to_restore_without_a_backup() {
+        let path = generate("lab-no-backup.db");
+
+        match restore(path.path()) {
+            Err(LabError::NoBackup(backup)) => {
+                assert_eq!(backup, sibling(path.path(), BACKUP_EXTENSION))
+            }
+            result => panic!("unexpected {:?}", result),
+        }
+        assert!(find(path.path(), "tracker.example").is_some());
+    }
+
+    #[test]
+    fn only_checks_the_browser_owning_the_database() {
+        let safari = Path::new("/Users/me").join(crate::discovery::DATABASE_PATH);
+        let firefox = Path::new("/home/me/.mozilla/firefox/abc.default/permissions.sqlite");
+        let chrome = Path::new("/home/me/.config/google-chrome/Default/DIPS");
+
+        assert_eq!(browser_processes(&safari), vec!["Safari"]);
+        assert_eq!(browser_processes(firefox), vec!["firefox"]);
+        assert_eq!(browser_processes(chrome), vec!["Google Chrome", "chrome"]);
+        assert!(browser_processes(Path::new("/tmp/observations.db")).is_empty());
+    }
+}
@@ -6,21 +6,98 @@ use serde::Deserialize;
 use std::io;
 use std::sync::mpsc;
 use std::thread;
-use std::time::{Duration, Instant};
-use std::{cmp, path::PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use std::{cmp, collections::BTreeMap, path::PathBuf};
 use structopt::StructOpt;
-use tui::{backend::CrosstermBackend, widgets::ListState, Terminal};
+use tui::{
+    backend::CrosstermBackend,
+    widgets::{ListState, TableState},
+    Terminal,
+};
+
+mod anonymize;
+
+mod clock;
+
+mod commands;
+use commands::Command;
+
+mod ios_backup;
+
+mod cookies;
+use cookies::Cookie;
+
+mod discovery;
+use discovery::{Candidate, SimulatorDevice};
 
 mod database;
-use database::Database;
+use database::{Domain, TemporaryDatabase};
+
+mod database_set;
+
+mod dips;
+
+mod firefox;
+use database_set::DatabaseSet;
+
+#[cfg(test)]
+mod fixture;
+
+mod stats_source;
+use stats_source::StatsSource;
+
+mod lab;
+use lab::{Edit, Prevalence};
+
+mod scenario;
+
+mod filter;
+use filter::Filter;
+
+mod navigation;
+use navigation::{Focus, Navigation};
+
+mod sort;
+use sort::{Column, Sort};
+
+mod search;
+use search::{Input, Search};
+
+mod scope;
+use scope::Scope;
+
+mod schema;
+
+mod demo;
 
 mod gui;
 use gui::Gui;
 
+mod website_data;
+
+mod domain_list;
+use domain_list::DomainList;
+
 mod domain_renderer;
 use domain_renderer::DomainRenderer;
 
-const DATABASE_PATH: &'static str = "Library/Containers/com.apple.Safari/Data/Library/WebKit/WebsiteData/ResourceLoadStatistics/observations.db";
+mod blocklists;
+use blocklists::BlocklistConfig;
+
+mod entities;
+use entities::{Entities, EntityTotals};
+
+mod graph;
+use graph::{Graph, View};
+
+mod graph_renderer;
+use graph_renderer::GraphRenderer;
+
+mod groups;
+use groups::{CrossGroup, Group, GroupConfig, GroupStats};
+
+mod group_renderer;
+use group_renderer::GroupRenderer;
 
 enum Event<I> {
     Input(I),
@@ -30,6 +107,10 @@ enum Event<I> {
 enum MenuItem {
     Home,
     Domains,
+    Simulators,
+    Profiles,
+    Groups,
+    Graph,
 }
 
 impl From<MenuItem> for usize {
@@ -37,6 +118,10 @@ impl From<MenuItem> for usize {
         match input {
             MenuItem::Home => 0,
             MenuItem::Domains => 1,
+            MenuItem::Simulators => 2,
+            MenuItem::Profiles => 3,
+            MenuItem::Groups => 4,
+            MenuItem::Graph => 5,
         }
     }
 }
@@ -48,20 +133,168 @@ struct Opts {
     /// Safari's SQLite path
     #[structopt(short, long)]
     path: Option<PathBuf>,
-    /// A list of comma separated domains.
+    /// A list of comma separated domains, `*` wildcards, `regex:` patterns or `!` exclusions.
     #[structopt(short, long, use_delimiter = true)]
     pub domains: Option<Vec<String>>,
+    /// Enables editing ITP's state from the Domains tab.
+    #[structopt(long)]
+    lab: bool,
+    /// Name or UDID of the iOS Simulator device whose database is used.
+    #[structopt(short, long)]
+    simulator: Option<String>,
+    /// Unencrypted iOS backup directory to extract the database from.
+    #[structopt(long)]
+    ios_backup: Option<PathBuf>,
+    /// Bundle identifier of the app embedding WKWebView whose database is used.
+    #[structopt(long)]
+    container: Option<String>,
+    /// Lists the ITP databases found on this machine.
+    #[structopt(long)]
+    list_databases: bool,
+    /// A list of comma separated databases shown along with the main one, as a single merged list.
+    #[structopt(long, use_delimiter = true)]
+    aggregate: Vec<PathBuf>,
+    /// Safari's Cookies.binarycookies, found next to the database by default.
+    #[structopt(long)]
+    cookies: Option<PathBuf>,
+    /// Entity list in Disconnect's entities.json format, telling which organization owns each domain.
+    #[structopt(long)]
+    entities: Option<PathBuf>,
+    /// A list of comma separated blocklists tagging the domains they list: hosts files, AdBlock rules or
+    /// Disconnect's services.json.
+    #[structopt(long, use_delimiter = true)]
+    blocklists: Vec<PathBuf>,
+    /// A list of comma separated columns of the Domains table: prevalence, iframes, requests, redirects,
+    /// last_seen and last_interaction.
+    #[structopt(long, use_delimiter = true)]
+    columns: Option<Vec<Column>>,
+    /// Runs against a temporary database fed by simulated navigation.
+    #[structopt(long)]
+    demo: bool,
+    #[structopt(subcommand)]
+    command: Option<Command>,
 }
 
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Deserialize, Default, Clone)]
 pub struct Config {
     pub path: Option<PathBuf>,
     domains: Option<Vec<String>>,
+    #[serde(default)]
+    pub lab: bool,
+    /// Databases aggregated with the one at `path`.
+    #[serde(default)]
+    pub aggregate: Vec<PathBuf>,
+    pub cookies: Option<PathBuf>,
+    pub entities: Option<PathBuf>,
+    #[serde(default, rename = "blocklist")]
+    pub blocklists: Vec<BlocklistConfig>,
+    #[serde(default, rename = "group")]
+    pub groups: Vec<GroupConfig>,
+    /// Optional columns of the Domains table, all of them by default.
+    pub columns: Option<Vec<Column>>,
+    /// Named filter expressions, referenced as `@name`.
+    #[serde(default)]
+    pub filters: BTreeMap<String, String>,
+    /// Built out of `domains` once normalized.
+    #[serde(skip)]
+    pub scope: Option<Scope>,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let config = fetch_config();
-    let db = Database::connect(config).expect("Couldn't connect to the database");
+    let mut opts = Opts::from_args();
+    let command = opts.command.take();
+
+    if opts.list_databases {
+        discovery::print_list(&discovery::all());
+        return Ok(());
+    }
+
+    if let Some(simulator) = &opts.simulator {
+        match discovery::simulator_path(simulator) {
+            Some(path) => opts.path = Some(path),
+            None => {
+                return Err(
+                    format!("No simulator named {} with a Safari database", simulator).into(),
+                )
+            }
+        }
+    }
+
+    if let Some(bundle_id) = &opts.container {
+        match discovery::app_container_path(bundle_id) {
+            Some(path) => opts.path = Some(path),
+            None => return Err(format!("No app container found for {}", bundle_id).into()),
+        }
+    }
+
+    if opts.demo && command.is_some() {
+        return Err("--demo only applies to the UI, run it without a subcommand".into());
+    }
+
+    // Databases created for this session only, removed on exit.
+    let mut temporary = Vec::new();
+
+    if let Some(backup) = &opts.ios_backup {
+        let database = TemporaryDatabase::new(ios_backup::extract(backup)?);
+        opts.path = Some(database.path().to_owned());
+        temporary.push(database);
+    }
+
+    if opts.demo {
+        let database = TemporaryDatabase::new(demo::prepare()?);
+        demo::spawn(database.path().to_owned(), Duration::from_millis(700));
+        opts.path = Some(database.path().to_owned());
+        temporary.push(database);
+    }
+
+    let mut config = fetch_config(opts);
+
+    // Shown once the UI starts as well, since it clears the terminal.
+    let mut scope_warnings = Vec::new();
+    if let Some(domains) = config.domains.take() {
+        let (domains, warnings) = scope::normalize(domains);
+
+        for warning in warnings.iter() {
+            eprintln!("Warning: {}", warning);
+        }
+        config.scope =
+            Some(Scope::parse(&domains).map_err(|e| format!("Invalid domain pattern: {}", e))?);
+        config.domains = Some(domains);
+        scope_warnings = warnings;
+    }
+
+    for (name, source) in config.filters.iter() {
+        Filter::parse(source, &config.filters)
+            .map_err(|e| format!("Invalid filter `{}`: {}", name, e))?;
+    }
+
+    if let Some(command) = command {
+        return command.run(config);
+    }
+
+    if config.path.is_none() {
+        config.path = Some(discovery::default_path());
+    }
+
+    let (groups, group_warnings) =
+        Group::parse(&config.groups).map_err(|e| format!("Invalid group: {}", e))?;
+    for warning in group_warnings {
+        eprintln!("Warning: {}", warning);
+        scope_warnings.push(warning);
+    }
+
+    let entities = match &config.entities {
+        Some(path) => Some(
+            Entities::from_file(path)
+                .map_err(|e| format!("Couldn't load {}: {}", path.display(), e))?,
+        ),
+        None => None,
+    };
+
+    let blocklists = blocklists::load(&config.blocklists)?;
+
+    let mut shown = labelled(&config);
+    let mut db = connect(&config, &shown).expect("Couldn't connect to the database");
 
     enable_raw_mode().expect("can run in raw mode");
 
@@ -83,10 +316,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
 
-            if last_tick.elapsed() >= tick_rate {
-                if let Ok(_) = tx.send(Event::Tick) {
-                    last_tick = Instant::now();
-                }
+            if last_tick.elapsed() >= tick_rate && tx.send(Event::Tick).is_ok() {
+                last_tick = Instant::now();
             }
         }
     });
@@ -97,58 +328,560 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     terminal.clear()?;
 
-    let menu_titles = vec!["Home", "Domains"];
+    let menu_titles = [
+        ("Home", 'h'),
+        ("Domains", 'd'),
+        ("Simulators", 's'),
+        ("Profiles", 'p'),
+        ("Groups", 'g'),
+        ("Graph", 'a'),
+    ];
     let mut active_menu_item = MenuItem::Home;
-    let mut domain_list_state = ListState::default();
-    domain_list_state.select(Some(0));
+    let mut domain_list_state = TableState::default();
+    // Lists domains owned by the same entity together.
+    let mut by_entity = false;
+    let mut search = Search::default();
+    let mut navigation = Navigation::default();
+    let mut sort = Sort::load();
+    let mut graph_view = View::default();
+    // Neighborhood drawn by the Graph tab along with its center and depth,
+    // laid out again once either changes or the domains were detailed again.
+    let mut graph: Option<(String, usize, Graph)> = None;
+    // Hides the optional columns of the Domains table.
+    let mut compact = false;
+    // Databases listed by the Simulators and Profiles tabs.
+    let mut sources: Vec<Candidate> = Vec::new();
+    let mut source_list_state = ListState::default();
+    let mut group_table_state = TableState::default();
+    group_table_state.select(first_index(&groups));
+    // Gathering group members goes through every domain, it is done once per
+    // opened database.
+    let mut group_stats: Option<(Vec<GroupStats>, Vec<CrossGroup>)> = None;
+    let mut status: Option<String> = match scope_warnings.is_empty() {
+        true => None,
+        false => Some(format!("Warning: {}", scope_warnings.join(", "))),
+    };
+    // Walking the website data directory and parsing the cookie jar are
+    // costly, they are read when a database is opened and reloaded on demand.
+    let mut website_data = scan_website_data(&config);
+    let mut cookies = read_cookies(&config).unwrap_or_else(|e| {
+        status = Some(e);
+        None
+    });
+    let mut domain_list = DomainList::build(
+        db.as_ref(),
+        entities.as_ref(),
+        &blocklists,
+        by_entity,
+        &search,
+        &sort,
+    );
+    domain_list_state.select(domain_list.clamp(None));
+    // Set once the database changed, its domains being detailed again before
+    // the next draw.
+    let mut rows_outdated = false;
+    // Set whenever the filters, the sort or the grouping change, the domains
+    // being listed again before the next draw.
+    let mut list_outdated = false;
+    // Browsers and the demo write to the databases while they are
+    // shown, they are reopened once they changed on disk.
+    let mut modified = last_modified(&config);
 
     loop {
+        if rows_outdated {
+            domain_list = DomainList::build(
+                db.as_ref(),
+                entities.as_ref(),
+                &blocklists,
+                by_entity,
+                &search,
+                &sort,
+            );
+        } else if list_outdated {
+            domain_list.arrange(by_entity, &search, &sort);
+        }
+
+        if rows_outdated || list_outdated {
+            domain_list_state.select(domain_list.clamp(domain_list_state.selected()));
+        }
+        if rows_outdated {
+            graph = None;
+        }
+        rows_outdated = false;
+        list_outdated = false;
+
+        if matches!(active_menu_item, MenuItem::Graph) {
+            let center = domain_list_state
+                .selected()
+                .and_then(|index| domain_list.domains.get(index))
+                .filter(|domain| domain.id != 0);
+            let laid_out = match (&graph, center) {
+                (Some((name, depth, _)), Some(center)) => {
+                    *name == center.name && *depth == graph_view.depth
+                }
+                (None, None) => true,
+                _ => false,
+            };
+
+            if !laid_out {
+                graph = center.map(|center| {
+                    let neighborhood = Graph::neighborhood(
+                        &center.name,
+                        domain_list.adjacency(),
+                        graph_view.depth,
+                    );
+                    (center.name.clone(), graph_view.depth, neighborhood)
+                });
+            }
+        }
+
         terminal.draw(|screen| {
             let main_panes = Gui::render_panes(screen.size());
             let tabs_widget = Gui::render_tabs(&menu_titles, active_menu_item.into());
-            let copyright_widget = Gui::render_copyright();
+            let footer_widget = match &status {
+                Some(message) => Gui::render_status(message),
+                None => Gui::render_copyright(),
+            };
 
             screen.render_widget(tabs_widget, main_panes[0]);
 
             match active_menu_item {
                 MenuItem::Home => screen.render_widget(Gui::render_home_pane(), main_panes[1]),
                 MenuItem::Domains => {
-                    let domain_list = db.get_domains().unwrap_or(Vec::new());
-                    let domain_renderer = match domain_list.is_empty() {
-                        true => DomainRenderer::new(&domain_list, None),
-                        false => {
-                            let selected_domain = domain_list
-                                .get(domain_list_state.selected().expect("domain to be selected"))
-                                .expect("domain to exist in list");
-
-                            DomainRenderer::new(&domain_list, Some(selected_domain))
+                    let domain_panes = Gui::render_domain_panes(main_panes[1]);
+                    let columns = visible_columns(&config, compact);
+                    let selected_domain = domain_list_state
+                        .selected()
+                        .and_then(|index| domain_list.domains.get(index));
+                    let entity = selected_domain
+                        .and_then(|domain| domain.entity.as_deref())
+                        .map(|name| EntityTotals::compute(name, domain_list.rows()));
+                    let related = domain_list.related(domain_list_state.selected());
+                    let related_selected = match navigation.focus {
+                        Focus::Related => {
+                            Some(navigation.related.min(related.len().saturating_sub(1)))
                         }
+                        Focus::List => None,
                     };
 
-                    domain_renderer.render(&db, screen, main_panes[1], &mut domain_list_state);
+                    DomainRenderer::new(&domain_list.domains, selected_domain)
+                        .with_entity(entity)
+                        .with_blocklists(!blocklists.is_empty())
+                        .with_cookies(cookies.as_deref())
+                        .with_website_data(website_data.as_deref())
+                        .with_search(&search)
+                        .with_columns(columns, &domain_list.interactions, sort)
+                        .with_related(&related, related_selected)
+                        .with_breadcrumbs(
+                            selected_domain.and_then(|domain| navigation.breadcrumbs(&domain.name)),
+                        )
+                        .render(db.as_ref(), screen, domain_panes[1], &mut domain_list_state);
+                    screen.render_widget(Gui::render_search_bar(&search), domain_panes[0]);
+                }
+                MenuItem::Simulators if sources.is_empty() => screen.render_widget(
+                    Gui::render_empty_pane(
+                        "Simulators",
+                        "No iOS Simulator device with a Safari database was found.",
+                        "Browse with Safari in a simulator to initialize it.",
+                    ),
+                    main_panes[1],
+                ),
+                MenuItem::Profiles if sources.is_empty() => screen.render_widget(
+                    Gui::render_empty_pane(
+                        "Profiles",
+                        "No browser database was found.",
+                        "Browse with Safari to initialize it.",
+                    ),
+                    main_panes[1],
+                ),
+                MenuItem::Graph => match &graph {
+                    Some((_, _, graph)) => {
+                        GraphRenderer::new(graph, &domain_list, &graph_view)
+                            .render(screen, main_panes[1]);
+                    }
+                    None => screen.render_widget(
+                        Gui::render_empty_pane(
+                            "Graph",
+                            "No domain is selected.",
+                            "Select a domain in the Domains tab to draw its neighborhood.",
+                        ),
+                        main_panes[1],
+                    ),
+                },
+                MenuItem::Groups if groups.is_empty() => screen.render_widget(
+                    Gui::render_empty_pane(
+                        "Groups",
+                        "No group of domains is configured.",
+                        "Add [[group]] entries with a name and domains to ~/.itprc.",
+                    ),
+                    main_panes[1],
+                ),
+                MenuItem::Groups => {
+                    let (stats, crossings) = group_stats.get_or_insert_with(|| {
+                        (
+                            groups
+                                .iter()
+                                .map(|group| GroupStats::compute(group, db.as_ref()))
+                                .collect(),
+                            groups::cross_group(&groups, &db.relationships()),
+                        )
+                    });
+
+                    GroupRenderer::new(stats, crossings).render(
+                        screen,
+                        main_panes[1],
+                        &mut group_table_state,
+                    );
                 }
+                MenuItem::Simulators | MenuItem::Profiles => screen.render_stateful_widget(
+                    Gui::render_candidates("Press Enter to open", &sources, config.path.as_ref()),
+                    main_panes[1],
+                    &mut source_list_state,
+                ),
             }
 
-            screen.render_widget(copyright_widget, main_panes[2]);
+            screen.render_widget(footer_widget, main_panes[2]);
         })?;
 
         match rx.recv()? {
+            Event::Input(event) if search.input == Some(Input::Filter) => match event.code {
+                KeyCode::Char(c) => search.expression.push(c),
+                KeyCode::Backspace => {
+                    search.expression.pop();
+                }
+                KeyCode::Esc => {
+                    search.expression = search
+                        .filter
+                        .as_ref()
+                        .map(|filter| filter.source().to_owned())
+                        .unwrap_or_default();
+                    search.input = None;
+                }
+                KeyCode::Enter if search.expression.trim().is_empty() => {
+                    search.filter = None;
+                    search.input = None;
+                    domain_list_state.select(Some(0));
+                    list_outdated = true;
+                }
+                KeyCode::Enter => match Filter::parse(&search.expression, &config.filters) {
+                    Ok(filter) => {
+                        search.filter = Some(filter);
+                        search.input = None;
+                        domain_list_state.select(Some(0));
+                        list_outdated = true;
+                        status = None;
+                    }
+                    Err(e) => status = Some(format!("Invalid filter: {}", e)),
+                },
+                _ => {}
+            },
+            Event::Input(event) if search.input == Some(Input::Query) => {
+                match event.code {
+                    KeyCode::Char(c) => search.query.push(c),
+                    KeyCode::Backspace => {
+                        search.query.pop();
+                    }
+                    KeyCode::Esc => {
+                        search.query.clear();
+                        search.input = None;
+                    }
+                    KeyCode::Enter => search.input = None,
+                    _ => {}
+                }
+
+                if let Some(index) = search.first_match(&domain_list.domains) {
+                    domain_list_state.select(Some(index));
+                }
+            }
             Event::Input(event) => match event.code {
                 KeyCode::Char('h') | KeyCode::Char('H') => active_menu_item = MenuItem::Home,
                 KeyCode::Char('d') | KeyCode::Char('D') => active_menu_item = MenuItem::Domains,
-                KeyCode::Down => {
-                    if let Some(selected) = domain_list_state.selected() {
-                        let amount_domains = db.domains_len().expect("failed to count domains");
-                        let index = cmp::min(selected as i32 + 1, amount_domains - 1);
-                        domain_list_state.select(Some(index as usize));
+                KeyCode::Char('s') | KeyCode::Char('S') => {
+                    sources = discovery::simulators()
+                        .iter()
+                        .map(SimulatorDevice::candidate)
+                        .collect();
+                    source_list_state.select(first_index(&sources));
+                    active_menu_item = MenuItem::Simulators;
+                }
+                KeyCode::Char('p') | KeyCode::Char('P') => {
+                    sources = discovery::candidates();
+                    source_list_state.select(first_index(&sources));
+                    active_menu_item = MenuItem::Profiles;
+                }
+                KeyCode::Char('g') | KeyCode::Char('G') => active_menu_item = MenuItem::Groups,
+                KeyCode::Char('a') | KeyCode::Char('A') => active_menu_item = MenuItem::Graph,
+                KeyCode::Char('+') | KeyCode::Char('=')
+                    if matches!(active_menu_item, MenuItem::Graph) =>
+                {
+                    graph_view.deeper();
+                }
+                KeyCode::Char('-') if matches!(active_menu_item, MenuItem::Graph) => {
+                    graph_view.shallower();
+                }
+                KeyCode::Char('0') if matches!(active_menu_item, MenuItem::Graph) => {
+                    graph_view.recenter();
+                }
+                KeyCode::Left if matches!(active_menu_item, MenuItem::Graph) => {
+                    graph_view.pan(-1, 0)
+                }
+                KeyCode::Right if matches!(active_menu_item, MenuItem::Graph) => {
+                    graph_view.pan(1, 0)
+                }
+                KeyCode::Up if matches!(active_menu_item, MenuItem::Graph) => graph_view.pan(0, 1),
+                KeyCode::Down if matches!(active_menu_item, MenuItem::Graph) => {
+                    graph_view.pan(0, -1)
+                }
+                KeyCode::Char('e') | KeyCode::Char('E')
+                    if entities.is_some() && matches!(active_menu_item, MenuItem::Domains) =>
+                {
+                    by_entity = !by_entity;
+                    domain_list_state.select(Some(0));
+                    list_outdated = true;
+                    status = Some(match by_entity {
+                        true => "Domains grouped by entity".to_owned(),
+                        false => {
+                            format!("Domains sorted by {}", sort.column.title().to_lowercase())
+                        }
+                    });
+                }
+                KeyCode::Char('o') | KeyCode::Char('O')
+                    if matches!(active_menu_item, MenuItem::Domains) =>
+                {
+                    sort.next_column(visible_columns(&config, compact));
+                    status = Some(save_sort(&sort));
+                    list_outdated = true;
+                }
+                KeyCode::Char('r') | KeyCode::Char('R')
+                    if matches!(active_menu_item, MenuItem::Domains) =>
+                {
+                    sort.descending = !sort.descending;
+                    status = Some(save_sort(&sort));
+                    list_outdated = true;
+                }
+                KeyCode::Char('c') | KeyCode::Char('C')
+                    if matches!(active_menu_item, MenuItem::Domains) =>
+                {
+                    compact = !compact;
+                }
+                KeyCode::Char('u') | KeyCode::Char('U')
+                    if matches!(active_menu_item, MenuItem::Domains) =>
+                {
+                    status = Some(match connect(&config, &shown) {
+                        Ok(source) => {
+                            db = source;
+                            group_stats = None;
+                            rows_outdated = true;
+                            modified = last_modified(&config);
+                            "Reloaded the database, cookies and website data".to_owned()
+                        }
+                        Err(e) => format!("Couldn't reopen the database: {}", e),
+                    });
+                    website_data = scan_website_data(&config);
+                    cookies = read_cookies(&config).unwrap_or_else(|e| {
+                        status = Some(e);
+                        None
+                    });
+                }
+                KeyCode::Char('/') if matches!(active_menu_item, MenuItem::Domains) => {
+                    search.query.clear();
+                    search.input = Some(Input::Query);
+                }
+                KeyCode::Char('f') | KeyCode::Char('F')
+                    if matches!(active_menu_item, MenuItem::Domains) =>
+                {
+                    search.input = Some(Input::Filter);
+                }
+                KeyCode::Esc if matches!(active_menu_item, MenuItem::Domains) => {
+                    search.query.clear();
+                }
+                KeyCode::Char(c @ '1'..='4') if matches!(active_menu_item, MenuItem::Domains) => {
+                    search.toggle(c as usize - '1' as usize);
+                    domain_list_state.select(Some(0));
+                    list_outdated = true;
+                }
+                KeyCode::Char('n') | KeyCode::Char('N')
+                    if matches!(active_menu_item, MenuItem::Domains) =>
+                {
+                    let from = domain_list_state.selected().unwrap_or(0);
+
+                    match search.next_match(
+                        &domain_list.domains,
+                        from,
+                        event.code == KeyCode::Char('n'),
+                    ) {
+                        Some(index) => domain_list_state.select(Some(index)),
+                        None if search.query.is_empty() => {
+                            status = Some("Press / to search domains first".to_owned())
+                        }
+                        None => status = Some(format!("No domain matches '{}'", search.query)),
+                    }
+                }
+                KeyCode::Tab if matches!(active_menu_item, MenuItem::Domains) => {
+                    navigation.toggle_focus();
+                }
+                KeyCode::Enter
+                    if matches!(active_menu_item, MenuItem::Domains)
+                        && navigation.focus == Focus::Related =>
+                {
+                    let selected = domain_list_state.selected();
+                    let related = domain_list.related(selected);
+                    let current = selected.and_then(|index| domain_list.domains.get(index));
+                    let target =
+                        related.get(navigation.related.min(related.len().saturating_sub(1)));
+
+                    if let (Some(current), Some(target)) = (current, target) {
+                        match domain_list.position(&target.domain) {
+                            Some(index) => {
+                                navigation.push(current.name.clone());
+                                domain_list_state.select(Some(index));
+                            }
+                            None => status = Some(unlisted(&domain_list, &target.domain)),
+                        }
+                    }
+                }
+                KeyCode::Backspace if matches!(active_menu_item, MenuItem::Domains) => {
+                    if let Some(name) = navigation.back() {
+                        match domain_list.position(&name) {
+                            Some(index) => domain_list_state.select(Some(index)),
+                            None => status = Some(unlisted(&domain_list, &name)),
+                        }
+                    }
+                }
+                KeyCode::Down
+                    if matches!(active_menu_item, MenuItem::Domains)
+                        && navigation.focus == Focus::Related =>
+                {
+                    let related = domain_list.related(domain_list_state.selected());
+
+                    navigation.related =
+                        cmp::min(navigation.related + 1, related.len().saturating_sub(1));
+                }
+                KeyCode::Up
+                    if matches!(active_menu_item, MenuItem::Domains)
+                        && navigation.focus == Focus::Related =>
+                {
+                    navigation.related = navigation.related.saturating_sub(1);
+                }
+                KeyCode::Down => match active_menu_item {
+                    MenuItem::Simulators | MenuItem::Profiles => {
+                        if let Some(selected) = source_list_state.selected() {
+                            let index = cmp::min(selected + 1, sources.len() - 1);
+                            source_list_state.select(Some(index));
+                        }
+                    }
+                    MenuItem::Groups => {
+                        if let Some(selected) = group_table_state.selected() {
+                            let index = cmp::min(selected + 1, groups.len() - 1);
+                            group_table_state.select(Some(index));
+                        }
+                    }
+                    _ => {
+                        navigation.clear();
+
+                        if let Some(selected) = domain_list_state.selected() {
+                            let index =
+                                cmp::min(selected + 1, domain_list.domains.len().saturating_sub(1));
+                            domain_list_state.select(Some(index));
+                        }
+                    }
+                },
+                KeyCode::Up
+                    if matches!(active_menu_item, MenuItem::Simulators | MenuItem::Profiles) =>
+                {
+                    if let Some(selected) = source_list_state.selected() {
+                        source_list_state.select(Some(selected.saturating_sub(1)));
                     }
                 }
                 KeyCode::Up => {
-                    if let Some(selected) = domain_list_state.selected() {
-                        let index = cmp::max(selected as i32 - 1, 0);
-                        domain_list_state.select(Some(index as usize));
+                    let table_state = match active_menu_item {
+                        MenuItem::Groups => &mut group_table_state,
+                        _ => {
+                            navigation.clear();
+                            &mut domain_list_state
+                        }
+                    };
+
+                    if let Some(selected) = table_state.selected() {
+                        table_state.select(Some(selected.saturating_sub(1)));
+                    }
+                }
+                KeyCode::Enter
+                    if matches!(active_menu_item, MenuItem::Simulators | MenuItem::Profiles) =>
+                {
+                    if let Some(source) = source_list_state
+                        .selected()
+                        .and_then(|index| sources.get(index))
+                    {
+                        // The current database stays open when the new one can't be.
+                        let mut opened = config.clone();
+                        opened.path = Some(source.path.clone());
+                        let mut opened_shown = shown.clone();
+                        if let Some(main) = opened_shown.first_mut() {
+                            *main = source.clone();
+                        }
+
+                        match connect(&opened, &opened_shown) {
+                            Ok(source_db) => {
+                                config = opened;
+                                shown = opened_shown;
+                                db = source_db;
+                                group_stats = None;
+                                rows_outdated = true;
+                                modified = last_modified(&config);
+                                website_data = scan_website_data(&config);
+                                domain_list_state.select(Some(0));
+                                navigation.clear();
+                                active_menu_item = MenuItem::Domains;
+                                status = Some(format!("Opened {}", source.label));
+                                cookies = read_cookies(&config).unwrap_or_else(|e| {
+                                    status = Some(e);
+                                    None
+                                });
+                            }
+                            Err(e) => {
+                                status = Some(format!("Couldn't open {}: {}", source.label, e))
+                            }
+                        }
                     }
                 }
+                KeyCode::Char('i')
+                | KeyCode::Char('I')
+                | KeyCode::Char('t')
+                | KeyCode::Char('T')
+                    if config.lab && matches!(active_menu_item, MenuItem::Domains) =>
+                {
+                    let edit = lab_edit(
+                        db.as_ref(),
+                        &domain_list.domains,
+                        domain_list_state.selected(),
+                        event.code,
+                    );
+                    let path = config
+                        .path
+                        .as_ref()
+                        .expect("database path to be configured");
+
+                    status = Some(match edit {
+                        Some(edit) => {
+                            match lab::apply(path, &[edit]).map(|()| connect(&config, &shown)) {
+                                Ok(Ok(source)) => {
+                                    db = source;
+                                    group_stats = None;
+                                    rows_outdated = true;
+                                    modified = last_modified(&config);
+                                    "Lab: database updated, restore it with `itp_tldr lab restore`"
+                                        .to_owned()
+                                }
+                                Ok(Err(e)) => {
+                                    format!("Lab: database updated but couldn't be reopened: {}", e)
+                                }
+                                Err(e) => format!("Lab: {}", e),
+                            }
+                        }
+                        None => "Lab: select an observed domain first".to_owned(),
+                    });
+                }
                 KeyCode::Char('q') | KeyCode::Char('Q') => {
                     disable_raw_mode()?;
                     terminal.show_cursor()?;
@@ -157,17 +890,160 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
                 _ => {}
             },
-            Event::Tick => {}
+            Event::Tick => {
+                let now = last_modified(&config);
+
+                if now != modified {
+                    modified = now;
+
+                    match connect(&config, &shown) {
+                        Ok(source) => {
+                            db = source;
+                            group_stats = None;
+                            rows_outdated = true;
+                        }
+                        Err(e) => status = Some(format!("Couldn't reopen the database: {}", e)),
+                    }
+                }
+            }
         }
     }
 
     Ok(())
 }
 
-fn fetch_config() -> Config {
-    let opts = Opts::from_args();
+/// A single database, or all of the `shown` ones merged when some are
+/// aggregated.
+fn connect(config: &Config, shown: &[Candidate]) -> rusqlite::Result<Box<dyn StatsSource>> {
+    if config.aggregate.is_empty() {
+        return stats_source::connect(config.clone());
+    }
+
+    Ok(Box::new(DatabaseSet::connect(shown, config.scope.clone())?))
+}
+
+/// The configured database followed by the aggregated ones, none unless some
+/// are aggregated. Labelling them looks through every database on this
+/// machine, so it's done once rather than on every reconnection.
+fn labelled(config: &Config) -> Vec<Candidate> {
+    if config.aggregate.is_empty() {
+        return Vec::new();
+    }
+
+    let known = discovery::all();
+
+    config
+        .path
+        .iter()
+        .chain(config.aggregate.iter())
+        .map(|path| Candidate {
+            label: discovery::label(path, &known),
+            path: path.clone(),
+        })
+        .collect()
+}
+
+/// Why the domain named `name` can't be selected.
+fn unlisted(domain_list: &DomainList, name: &str) -> String {
+    match domain_list.filtered_out(name) {
+        true => format!("{} is filtered out", name),
+        false => format!("{} isn't in the domain list", name),
+    }
+}
+
+/// Optional columns of the Domains table, unless compacted.
+fn visible_columns(config: &Config, compact: bool) -> &[Column] {
+    match (compact, &config.columns) {
+        (true, _) => &[],
+        (false, Some(columns)) => columns,
+        (false, None) => &Column::OPTIONAL,
+    }
+}
+
+/// Remembers the sort for the next sessions, telling how domains are sorted.
+fn save_sort(sort: &Sort) -> String {
+    let order = match sort.descending {
+        true => "descending",
+        false => "ascending",
+    };
+
+    match sort.save() {
+        Ok(()) => format!("Sorted by {} {}", sort.column.title().to_lowercase(), order),
+        Err(e) => format!("Couldn't remember the sort: {}", e),
+    }
+}
+
+/// When each shown database was last written to.
+fn last_modified(config: &Config) -> Vec<Option<SystemTime>> {
+    config
+        .path
+        .iter()
+        .chain(config.aggregate.iter())
+        .map(|path| database::modified(path))
+        .collect()
+}
+
+fn scan_website_data(config: &Config) -> Option<Vec<website_data::Entry>> {
+    config
+        .path
+        .as_deref()
+        .and_then(website_data::locate)
+        .map(|root| website_data::scan(&root))
+}
+
+/// Cookie jar of the configured database, `None` when it has none.
+fn read_cookies(config: &Config) -> Result<Option<Vec<Cookie>>, String> {
+    let path = match config
+        .cookies
+        .clone()
+        .or_else(|| config.path.as_deref().and_then(cookies::locate))
+    {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+
+    cookies::read(&path)
+        .map(Some)
+        .map_err(|e| format!("Couldn't read {}: {}", path.display(), e))
+}
+
+fn first_index<T>(list: &[T]) -> Option<usize> {
+    match list.is_empty() {
+        true => None,
+        false => Some(0),
+    }
+}
 
-    let mut config = match read_config() {
+/// Edit toggling the interaction ('i') or prevalence ('t') of the selected domain.
+fn lab_edit(
+    db: &dyn StatsSource,
+    domains: &[Domain],
+    selected: Option<usize>,
+    key: KeyCode,
+) -> Option<Edit> {
+    let domain = domains.get(selected?).filter(|d| d.id != 0)?;
+    let info = db.get_info(domain).ok()?;
+
+    match key {
+        KeyCode::Char('i') | KeyCode::Char('I') => {
+            let at = match info.had_user_interaction {
+                true => None,
+                false => Some(clock::now()),
+            };
+            Some(Edit::Interaction(info.name, at))
+        }
+        _ => {
+            let prevalence = match info.prevalent {
+                true => Prevalence::None,
+                false => Prevalence::Prevalent,
+            };
+            Some(Edit::Prevalence(info.name, prevalence))
+        }
+    }
+}
+
+fn fetch_config(opts: Opts) -> Config {
+    match read_config() {
         Some(config) => {
             let mut config: Config = toml::from_str(&config).expect("config to follow TOML format");
 
@@ -179,24 +1055,52 @@ fn fetch_config() -> Config {
                 config.domains = opts.domains;
             }
 
+            config.lab |= opts.lab;
+
+            if !opts.aggregate.is_empty() {
+                config.aggregate = opts.aggregate;
+            }
+
+            if opts.cookies.is_some() {
+                config.cookies = opts.cookies;
+            }
+
+            if opts.entities.is_some() {
+                config.entities = opts.entities;
+            }
+
+            if opts.columns.is_some() {
+                config.columns = opts.columns;
+            }
+
+            if !opts.blocklists.is_empty() {
+                config.blocklists = opts
+                    .blocklists
+                    .into_iter()
+                    .map(BlocklistConfig::new)
+                    .collect();
+            }
+
             config
         }
         None => Config {
             path: opts.path,
             domains: opts.domains,
+            lab: opts.lab,
+            aggregate: opts.aggregate,
+            cookies: opts.cookies,
+            entities: opts.entities,
+            blocklists: opts
+                .blocklists
+                .into_iter()
+                .map(BlocklistConfig::new)
+                .collect(),
+            groups: Vec::new(),
+            columns: opts.columns,
+            filters: BTreeMap::new(),
+            scope: None,
         },
-    };
-
-    if config.path.is_none() {
-        let mut db_path = match dirs::home_dir() {
-            Some(dir) => dir,
-            None => panic!("Could not infer home directory."),
-        };
-        db_path.push(DATABASE_PATH);
-        config.path = Some(db_path);
     }
-
-    config
 }
 
 fn read_config() -> Option<String> {
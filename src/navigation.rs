@@ -0,0 +1,93 @@
+use crate::stats_source::Relationship;
+
+/// Which part of the Domains tab the arrow keys move in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Focus {
+    List,
+    Related,
+}
+
+/// A domain the selected one relates to, as in "iframed under news.example".
+#[derive(Debug, Clone, PartialEq)]
+pub struct Related {
+    pub relation: String,
+    pub domain: String,
+}
+
+/// Domains related to `name` through `relationships`, in both directions.
+pub fn related(name: &str, relationships: &[Relationship]) -> Vec<Related> {
+    let mut related: Vec<_> = relationships
+        .iter()
+        .filter_map(
+            |relationship| match (&relationship.from, &relationship.to) {
+                (from, to) if from == name => Some(Related {
+                    relation: relationship.relation.verb().to_owned(),
+                    domain: to.clone(),
+                }),
+                (from, to) if to == name => Some(Related {
+                    relation: relationship.relation.inverse_verb().to_owned(),
+                    domain: from.clone(),
+                }),
+                _ => None,
+            },
+        )
+        .collect();
+
+    related.sort_by(|a, b| (&a.relation, &a.domain).cmp(&(&b.relation, &b.domain)));
+    related.dedup();
+    related
+}
+
+/// Drill-down state of the Domains tab: the domains jumped from, most recent
+/// last, and the related domain selected in the details.
+#[derive(Debug)]
+pub struct Navigation {
+    history: Vec<String>,
+    pub focus: Focus,
+    pub related: usize,
+}
+
+impl Default for Navigation {
+    fn default() -> Self {
+        Navigation {
+            history: Vec::new(),
+            focus: Focus::List,
+            related: 0,
+        }
+    }
+}
+
+impl Navigation {
+    /// Remembers `from` before jumping to one of its related domains.
+    pub fn push(&mut self, from: String) {
+        self.history.push(from);
+        self.related = 0;
+    }
+
+    /// The domain jumped from last, forgotten once returned to.
+    pub fn back(&mut self) -> Option<String> {
+        self.related = 0;
+        self.history.pop()
+    }
+
+    /// Moving through the list by hand leaves the drill-down.
+    pub fn clear(&mut self) {
+        self.history.clear();
+        self.related = 0;
+    }
+
+    /// The path to `current`, as in "news.example › tracker.example".
+    pub fn breadcrumbs(&self, current: &str) -> Option<String> {
+        match self.history.is_empty() {
+            true => None,
+            false => Some(format!("{} › {}", self.history.join(" › "), current)),
+        }
+    }
+
+    pub fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            Focus::List => Focus::Related,
+            Focus::Related => Focus::List,
+        };
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension, Result as SQLiteResult};
+use serde::Deserialize;
+
+use crate::clock;
+use crate::schema;
+
+const DOMAIN_ID: &str = "SELECT domainID FROM ObservedDomains WHERE registrableDomain = ?";
+const INSERT_DOMAIN: &str = "INSERT INTO ObservedDomains (registrableDomain, lastSeen, hadUserInteraction, mostRecentUserInteractionTime, grandfathered, isPrevalent, isVeryPrevalent, dataRecordsRemoved, timesAccessedAsFirstPartyDueToUserInteraction, timesAccessedAsFirstPartyDueToStorageAccessAPI, isScheduledForAllButCookieDataRemoval, mostRecentWebPushInteractionTime) VALUES (?, ?, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0)";
+const UPDATE_DOMAIN: &str = "UPDATE ObservedDomains SET hadUserInteraction = ?, mostRecentUserInteractionTime = ?, isPrevalent = ?, isVeryPrevalent = ?, timesAccessedAsFirstPartyDueToUserInteraction = ?, timesAccessedAsFirstPartyDueToStorageAccessAPI = ? WHERE domainID = ?";
+const SET_INTERACTION: &str = "UPDATE ObservedDomains SET hadUserInteraction = 1, mostRecentUserInteractionTime = ?, timesAccessedAsFirstPartyDueToUserInteraction = timesAccessedAsFirstPartyDueToUserInteraction + 1 WHERE domainID = ?";
+const TOUCH_DOMAIN: &str = "UPDATE ObservedDomains SET lastSeen = ? WHERE domainID = ?";
+const ADD_TOP_LEVEL: &str = "INSERT OR IGNORE INTO TopLevelDomains (topLevelDomainID) VALUES (?)";
+const ADD_SUBFRAME: &str =
+    "INSERT OR IGNORE INTO SubframeUnderTopFrameDomains (subFrameDomainID, topFrameDomainID) VALUES (?, ?)";
+const ADD_SUBRESOURCE: &str = "INSERT OR IGNORE INTO SubresourceUnderTopFrameDomains (subresourceDomainID, topFrameDomainID) VALUES (?, ?)";
+const ADD_REDIRECT_TO: &str =
+    "INSERT OR IGNORE INTO TopFrameUniqueRedirectsTo (sourceDomainID, toDomainID) VALUES (?, ?)";
+const ADD_REDIRECT_FROM: &str =
+    "INSERT OR IGNORE INTO TopFrameUniqueRedirectsFrom (targetDomainID, fromDomainID) VALUES (?, ?)";
+const ADD_STORAGE_ACCESS: &str = "INSERT OR IGNORE INTO StorageAccessUnderTopFrameDomains (domainID, topLevelDomainID) VALUES (?, ?)";
+
+/// A TOML description of ITP's state.
+///
+/// ```toml
+/// [[domain]]
+/// name = "tracker.example"
+/// prevalent = true
+/// iframed_under = 12
+/// redirected_from = ["news.example", "shop.example", "blog.example"]
+/// ```
+#[derive(Debug, Deserialize, Default)]
+pub struct Scenario {
+    #[serde(default, rename = "domain")]
+    pub domains: Vec<DomainScenario>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct DomainScenario {
+    pub name: String,
+    pub prevalent: bool,
+    pub very_prevalent: bool,
+    pub user_interaction: bool,
+    /// Seconds since the Unix epoch, defaults to now when `user_interaction` is set.
+    pub interaction_time: Option<f64>,
+    pub first_party_interactions: i32,
+    pub first_party_store_access: i32,
+    pub iframed_under: Sites,
+    pub subresource_under: Sites,
+    pub redirected_from: Sites,
+    pub redirects_to: Sites,
+    pub storage_access_under: Sites,
+}
+
+/// Either an amount of synthetic sites or the exact domains to use.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Sites {
+    Count(usize),
+    Names(Vec<String>),
+}
+
+impl Default for Sites {
+    fn default() -> Self {
+        Sites::Count(0)
+    }
+}
+
+impl Sites {
+    fn names(&self) -> Vec<String> {
+        match self {
+            Sites::Count(count) => (1..=*count).map(synthetic_site).collect(),
+            Sites::Names(names) => names.clone(),
+        }
+    }
+}
+
+/// Synthetic first parties are named after their index, so every domain in a
+/// scenario shares the same pool of sites.
+fn synthetic_site(index: usize) -> String {
+    format!("site-{}.example", index)
+}
+
+impl Scenario {
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Writes the scenario to a new database at `path`.
+    pub fn generate(&self, path: &Path) -> SQLiteResult<()> {
+        let mut connection = Connection::open(path)?;
+        schema::create(&connection)?;
+
+        let transaction = connection.transaction()?;
+        {
+            let mut observations = Observations::new(&transaction);
+            for domain in self.domains.iter() {
+                observations.add(domain)?;
+            }
+        }
+
+        transaction.commit()
+    }
+}
+
+/// Records observations the way WebKit does, creating domains on first sight.
+pub struct Observations<'c> {
+    connection: &'c Connection,
+    ids: HashMap<String, i64>,
+}
+
+impl<'c> Observations<'c> {
+    pub fn new(connection: &'c Connection) -> Self {
+        Self {
+            connection,
+            ids: HashMap::new(),
+        }
+    }
+
+    pub fn domain_id(&mut self, domain: &str) -> SQLiteResult<i64> {
+        if let Some(id) = self.ids.get(domain) {
+            return Ok(*id);
+        }
+
+        let existing = self
+            .connection
+            .query_row(DOMAIN_ID, params![domain], |row| row.get(0))
+            .optional()?;
+
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                self.connection
+                    .execute(INSERT_DOMAIN, params![domain, clock::now()])?;
+                self.connection.last_insert_rowid()
+            }
+        };
+
+        self.ids.insert(domain.to_owned(), id);
+        Ok(id)
+    }
+
+    /// Marks `domain` as seen now.
+    pub fn visit(&mut self, domain: &str) -> SQLiteResult<i64> {
+        let id = self.domain_id(domain)?;
+        self.connection
+            .execute(TOUCH_DOMAIN, params![clock::now(), id])?;
+        Ok(id)
+    }
+
+    pub fn top_frame(&mut self, domain: &str) -> SQLiteResult<i64> {
+        let id = self.visit(domain)?;
+        self.connection.execute(ADD_TOP_LEVEL, params![id])?;
+        Ok(id)
+    }
+
+    pub fn interaction(&mut self, domain: &str) -> SQLiteResult<()> {
+        let id = self.top_frame(domain)?;
+        self.connection
+            .execute(SET_INTERACTION, params![clock::now(), id])?;
+        Ok(())
+    }
+
+    pub fn subframe(&mut self, subframe: &str, top_frame: &str) -> SQLiteResult<()> {
+        let top_frame_id = self.top_frame(top_frame)?;
+        let subframe_id = self.visit(subframe)?;
+        self.connection
+            .execute(ADD_SUBFRAME, params![subframe_id, top_frame_id])?;
+        Ok(())
+    }
+
+    pub fn subresource(&mut self, subresource: &str, top_frame: &str) -> SQLiteResult<()> {
+        let top_frame_id = self.top_frame(top_frame)?;
+        let subresource_id = self.visit(subresource)?;
+        self.connection
+            .execute(ADD_SUBRESOURCE, params![subresource_id, top_frame_id])?;
+        Ok(())
+    }
+
+    pub fn redirect(&mut self, from: &str, to: &str) -> SQLiteResult<()> {
+        let from_id = self.top_frame(from)?;
+        let to_id = self.top_frame(to)?;
+        self.connection
+            .execute(ADD_REDIRECT_TO, params![from_id, to_id])?;
+        self.connection
+            .execute(ADD_REDIRECT_FROM, params![to_id, from_id])?;
+        Ok(())
+    }
+
+    pub fn storage_access(&mut self, domain: &str, top_frame: &str) -> SQLiteResult<()> {
+        let top_frame_id = self.top_frame(top_frame)?;
+        let id = self.visit(domain)?;
+        self.connection
+            .execute(ADD_STORAGE_ACCESS, params![id, top_frame_id])?;
+        Ok(())
+    }
+
+    fn add(&mut self, domain: &DomainScenario) -> SQLiteResult<()> {
+        let id = self.domain_id(&domain.name)?;
+
+        for site in domain.iframed_under.names() {
+            self.subframe(&domain.name, &site)?;
+        }
+        for site in domain.subresource_under.names() {
+            self.subresource(&domain.name, &site)?;
+        }
+        for site in domain.redirected_from.names() {
+            self.redirect(&site, &domain.name)?;
+        }
+        for site in domain.redirects_to.names() {
+            self.redirect(&domain.name, &site)?;
+        }
+        for site in domain.storage_access_under.names() {
+            self.storage_access(&domain.name, &site)?;
+        }
+
+        let interaction_time = match domain.user_interaction {
+            true => domain.interaction_time.unwrap_or_else(clock::now),
+            false => 0.0,
+        };
+
+        self.connection.execute(
+            UPDATE_DOMAIN,
+            params![
+                domain.user_interaction,
+                interaction_time,
+                domain.prevalent || domain.very_prevalent,
+                domain.very_prevalent,
+                domain.first_party_interactions,
+                domain.first_party_store_access,
+                id
+            ],
+        )?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{Database, Domain, TemporaryDatabase};
+    use crate::fixture;
+    use crate::stats_source::StatsSource;
+    use crate::Config;
+
+    /// Database generated from one of the scenarios shipped in `scenarios/`.
+    fn generate(name: &str) -> (Database, TemporaryDatabase) {
+        let scenario = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("scenarios")
+            .join(name);
+        let path = TemporaryDatabase::new(fixture::temporary_path(name));
+
+        Scenario::from_file(&scenario)
+            .unwrap()
+            .generate(path.path())
+            .unwrap();
+
+        let db = Database::connect(Config {
+            path: Some(path.path().to_owned()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        (db, path)
+    }
+
+    fn find(db: &Database, name: &str) -> Domain {
+        let domain = db.find(name).unwrap();
+        db.get_info(&domain).unwrap()
+    }
+
+    #[test]
+    fn tracker_scenario() {
+        let (db, _path) = generate("tracker.toml");
+
+        let tracker = find(&db, "tracker.example");
+        assert!(tracker.prevalent);
+        assert!(!tracker.very_prevalent);
+        assert!(!tracker.had_user_interaction);
+
+        let interaction = db.domain_interaction(&tracker);
+        assert_eq!(interaction.iframes, 12);
+        assert_eq!(interaction.requests, 8);
+        assert_eq!(interaction.redirects, 3);
+
+        let news = find(&db, "news.example");
+        assert!(news.had_user_interaction);
+        assert_eq!(news.first_party_interaction, 4);
+
+        let login = find(&db, "login.example");
+        assert_eq!(login.last_interaction, Some(1609459200.0));
+        assert_eq!(
+            db.domain_interaction(&find(&db, "shop.example")).redirects,
+            1
+        );
+
+        // Synthetic sites are shared by every domain of the scenario.
+        assert!(db.find("site-12.example").is_some());
+        assert!(db.find("site-13.example").is_none());
+    }
+
+    #[test]
+    fn sites_are_counted_or_named() {
+        assert_eq!(
+            Sites::Count(2).names(),
+            vec!["site-1.example", "site-2.example"]
+        );
+        assert_eq!(
+            Sites::Names(vec!["news.example".to_owned()]).names(),
+            vec!["news.example"]
+        );
+        assert!(Sites::default().names().is_empty());
+    }
+}
@@ -0,0 +1,93 @@
+use rusqlite::{Connection, Result as SQLiteResult};
+
+/// ITP tables as created by WebKit's `ResourceLoadStatisticsStore`.
+const SCHEMA: &str = "
+CREATE TABLE ObservedDomains (
+    domainID INTEGER PRIMARY KEY, registrableDomain TEXT NOT NULL UNIQUE ON CONFLICT FAIL,
+    lastSeen REAL NOT NULL, hadUserInteraction INTEGER NOT NULL,
+    mostRecentUserInteractionTime REAL NOT NULL, grandfathered INTEGER NOT NULL,
+    isPrevalent INTEGER NOT NULL, isVeryPrevalent INTEGER NOT NULL,
+    dataRecordsRemoved INTEGER NOT NULL,
+    timesAccessedAsFirstPartyDueToUserInteraction INTEGER NOT NULL,
+    timesAccessedAsFirstPartyDueToStorageAccessAPI INTEGER NOT NULL,
+    isScheduledForAllButCookieDataRemoval INTEGER NOT NULL,
+    mostRecentWebPushInteractionTime REAL NOT NULL
+);
+CREATE TABLE TopLevelDomains (
+    topLevelDomainID INTEGER UNIQUE NOT NULL,
+    FOREIGN KEY(topLevelDomainID) REFERENCES ObservedDomains(domainID) ON DELETE CASCADE
+);
+CREATE TABLE StorageAccessUnderTopFrameDomains (
+    domainID INTEGER NOT NULL ON CONFLICT FAIL, topLevelDomainID INTEGER NOT NULL ON CONFLICT FAIL,
+    FOREIGN KEY(domainID) REFERENCES ObservedDomains(domainID) ON DELETE CASCADE,
+    FOREIGN KEY(topLevelDomainID) REFERENCES TopLevelDomains(topLevelDomainID) ON DELETE CASCADE
+);
+CREATE TABLE TopFrameUniqueRedirectsTo (
+    sourceDomainID INTEGER NOT NULL, toDomainID INTEGER NOT NULL,
+    FOREIGN KEY(sourceDomainID) REFERENCES TopLevelDomains(topLevelDomainID) ON DELETE CASCADE,
+    FOREIGN KEY(toDomainID) REFERENCES ObservedDomains(domainID) ON DELETE CASCADE
+);
+CREATE TABLE TopFrameUniqueRedirectsFrom (
+    targetDomainID INTEGER NOT NULL, fromDomainID INTEGER NOT NULL,
+    FOREIGN KEY(targetDomainID) REFERENCES TopLevelDomains(topLevelDomainID) ON DELETE CASCADE,
+    FOREIGN KEY(fromDomainID) REFERENCES ObservedDomains(domainID) ON DELETE CASCADE
+);
+CREATE TABLE TopFrameLinkDecorationsFrom (
+    toDomainID INTEGER NOT NULL, fromDomainID INTEGER NOT NULL,
+    FOREIGN KEY(toDomainID) REFERENCES TopLevelDomains(topLevelDomainID) ON DELETE CASCADE,
+    FOREIGN KEY(fromDomainID) REFERENCES ObservedDomains(domainID) ON DELETE CASCADE
+);
+CREATE TABLE TopFrameLoadedThirdPartyScripts (
+    topFrameDomainID INTEGER NOT NULL, subresourceDomainID INTEGER NOT NULL,
+    FOREIGN KEY(topFrameDomainID) REFERENCES TopLevelDomains(topLevelDomainID) ON DELETE CASCADE,
+    FOREIGN KEY(subresourceDomainID) REFERENCES ObservedDomains(domainID) ON DELETE CASCADE
+);
+CREATE TABLE SubframeUnderTopFrameDomains (
+    subFrameDomainID INTEGER NOT NULL, topFrameDomainID INTEGER NOT NULL,
+    FOREIGN KEY(subFrameDomainID) REFERENCES ObservedDomains(domainID) ON DELETE CASCADE,
+    FOREIGN KEY(topFrameDomainID) REFERENCES TopLevelDomains(topLevelDomainID) ON DELETE CASCADE
+);
+CREATE TABLE SubresourceUnderTopFrameDomains (
+    subresourceDomainID INTEGER NOT NULL, topFrameDomainID INTEGER NOT NULL,
+    FOREIGN KEY(subresourceDomainID) REFERENCES ObservedDomains(domainID) ON DELETE CASCADE,
+    FOREIGN KEY(topFrameDomainID) REFERENCES TopLevelDomains(topLevelDomainID) ON DELETE CASCADE
+);
+CREATE TABLE SubresourceUniqueRedirectsTo (
+    subresourceDomainID INTEGER NOT NULL, toDomainID INTEGER NOT NULL,
+    FOREIGN KEY(subresourceDomainID) REFERENCES ObservedDomains(domainID) ON DELETE CASCADE,
+    FOREIGN KEY(toDomainID) REFERENCES ObservedDomains(domainID) ON DELETE CASCADE
+);
+CREATE TABLE SubresourceUniqueRedirectsFrom (
+    subresourceDomainID INTEGER NOT NULL, fromDomainID INTEGER NOT NULL,
+    FOREIGN KEY(subresourceDomainID) REFERENCES ObservedDomains(domainID) ON DELETE CASCADE,
+    FOREIGN KEY(fromDomainID) REFERENCES ObservedDomains(domainID) ON DELETE CASCADE
+);
+CREATE TABLE OperatingDates (
+    year INTEGER NOT NULL, month INTEGER NOT NULL, monthDay INTEGER NOT NULL
+);
+CREATE UNIQUE INDEX StorageAccessUnderTopFrameDomains_domainID_topLevelDomainID
+    ON StorageAccessUnderTopFrameDomains (domainID, topLevelDomainID);
+CREATE UNIQUE INDEX TopFrameUniqueRedirectsTo_sourceDomainID_toDomainID
+    ON TopFrameUniqueRedirectsTo (sourceDomainID, toDomainID);
+CREATE UNIQUE INDEX TopFrameUniqueRedirectsFrom_targetDomainID_fromDomainID
+    ON TopFrameUniqueRedirectsFrom (targetDomainID, fromDomainID);
+CREATE UNIQUE INDEX TopFrameLinkDecorationsFrom_toDomainID_fromDomainID
+    ON TopFrameLinkDecorationsFrom (toDomainID, fromDomainID);
+CREATE UNIQUE INDEX TopFrameLoadedThirdPartyScripts_topFrameDomainID_subresourceDomainID
+    ON TopFrameLoadedThirdPartyScripts (topFrameDomainID, subresourceDomainID);
+CREATE UNIQUE INDEX SubframeUnderTopFrameDomains_subFrameDomainID_topFrameDomainID
+    ON SubframeUnderTopFrameDomains (subFrameDomainID, topFrameDomainID);
+CREATE UNIQUE INDEX SubresourceUnderTopFrameDomains_subresourceDomainID_topFrameDomainID
+    ON SubresourceUnderTopFrameDomains (subresourceDomainID, topFrameDomainID);
+CREATE UNIQUE INDEX SubresourceUniqueRedirectsTo_subresourceDomainID_toDomainID
+    ON SubresourceUniqueRedirectsTo (subresourceDomainID, toDomainID);
+CREATE UNIQUE INDEX SubresourceUniqueRedirectsFrom_subresourceDomainID_fromDomainID
+    ON SubresourceUniqueRedirectsFrom (subresourceDomainID, fromDomainID);
+CREATE UNIQUE INDEX OperatingDates_year_month_monthDay
+    ON OperatingDates (year, month, monthDay);
+";
+
+/// Creates the ITP tables in an empty database.
+pub fn create(connection: &Connection) -> SQLiteResult<()> {
+    connection.execute_batch(SCHEMA)
+}
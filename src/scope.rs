@@ -0,0 +1,345 @@
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+
+use regex::Regex;
+
+use crate::database::Domain;
+
+const REGEX_PREFIX: &str = "regex:";
+const EXCLUDE_PREFIX: &str = "!";
+
+/// A configured domain, matched against registrable domains.
+#[derive(Debug, Clone)]
+enum Pattern {
+    Exact(String),
+    /// `*` standing for any sequence of characters, such as `example.*` for
+    /// every regional domain. A leading `*.` matches the bare domain as well.
+    Wildcard(Regex),
+    /// `regex:^cdn\d+\.`, searched anywhere in the domain unless anchored.
+    Regex(Regex),
+}
+
+impl Pattern {
+    fn parse(pattern: &str) -> Result<Self, regex::Error> {
+        if let Some(expression) = pattern.strip_prefix(REGEX_PREFIX) {
+            return Ok(Pattern::Regex(Regex::new(expression)?));
+        }
+
+        if !pattern.contains('*') {
+            return Ok(Pattern::Exact(pattern.to_owned()));
+        }
+
+        let (subdomains, pattern) = match pattern.strip_prefix("*.") {
+            Some(pattern) => (r"(.*\.)?", pattern),
+            None => ("", pattern),
+        };
+        let expression = pattern
+            .split('*')
+            .map(regex::escape)
+            .collect::<Vec<_>>()
+            .join(".*");
+
+        Ok(Pattern::Wildcard(Regex::new(&format!(
+            "^{}{}$",
+            subdomains, expression
+        ))?))
+    }
+
+    fn matches(&self, domain: &str) -> bool {
+        match self {
+            Pattern::Exact(name) => name == domain,
+            Pattern::Wildcard(regex) | Pattern::Regex(regex) => regex.is_match(domain),
+        }
+    }
+}
+
+/// Domains to be listed: exact domains are always listed, observed or not,
+/// followed by the observed domains matching a pattern. Patterns starting
+/// with `!` exclude the domains they match, every observed domain being
+/// listed when there are only exclusions.
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl Scope {
+    pub fn parse(patterns: &[String]) -> Result<Self, regex::Error> {
+        let mut scope = Scope::default();
+
+        for pattern in patterns {
+            match pattern.strip_prefix(EXCLUDE_PREFIX) {
+                Some(pattern) => scope.exclude.push(Pattern::parse(pattern)?),
+                None => scope.include.push(Pattern::parse(pattern)?),
+            }
+        }
+
+        Ok(scope)
+    }
+
+    /// Whether `domain` is within the scope.
+    pub fn matches(&self, domain: &str) -> bool {
+        let included = self.include.is_empty() || self.include.iter().any(|p| p.matches(domain));
+        included && !self.is_excluded(domain)
+    }
+
+    fn is_excluded(&self, domain: &str) -> bool {
+        self.exclude.iter().any(|pattern| pattern.matches(domain))
+    }
+
+    /// The scoped domains out of every `observed` one, unobserved exact
+    /// domains being listed with an id of 0.
+    pub fn select(&self, observed: Vec<Domain>) -> Vec<Domain> {
+        let by_name: HashMap<&str, &Domain> = observed
+            .iter()
+            .map(|domain| (domain.name.as_str(), domain))
+            .collect();
+        let mut exact: HashSet<&str> = HashSet::new();
+        let mut domains: Vec<Domain> = Vec::new();
+
+        for pattern in self.include.iter() {
+            if let Pattern::Exact(name) = pattern {
+                if self.is_excluded(name) || !exact.insert(name) {
+                    continue;
+                }
+
+                domains.push(match by_name.get(name.as_str()) {
+                    Some(domain) => (*domain).clone(),
+                    None => Domain {
+                        name: name.to_owned(),
+                        ..Default::default()
+                    },
+                });
+            }
+        }
+
+        let mut listed: HashSet<i64> = domains.iter().map(|domain| domain.id).collect();
+        let only_exclusions = self.include.is_empty();
+
+        for domain in observed {
+            let included = only_exclusions
+                || self
+                    .include
+                    .iter()
+                    .any(|p| !matches!(p, Pattern::Exact(_)) && p.matches(&domain.name));
+
+            if included && !self.is_excluded(&domain.name) && listed.insert(domain.id) {
+                domains.push(domain);
+            }
+        }
+
+        domains
+    }
+}
+
+/// Registrable domain of a configured domain or URL, which is what WebKit
+/// records: `https://www.shop.example.co.uk/cart` becomes `example.co.uk`.
+///
+/// Hosts without a registrable domain, such as `localhost` or IP addresses,
+/// are kept as they are.
+pub fn registrable_domain(input: &str) -> String {
+    let host = host(input);
+
+    if host.parse::<IpAddr>().is_ok() {
+        return host;
+    }
+
+    match psl::domain_str(&host) {
+        Some(domain) => domain.to_owned(),
+        None => host,
+    }
+}
+
+/// Normalizes configured domains, along with a warning for every one that
+/// changed. Patterns are left as they are.
+pub fn normalize(domains: Vec<String>) -> (Vec<String>, Vec<String>) {
+    let mut warnings = Vec::new();
+
+    let domains = domains
+        .into_iter()
+        .map(|input| {
+            let (prefix, domain) = match input.strip_prefix(EXCLUDE_PREFIX) {
+                Some(domain) => (EXCLUDE_PREFIX, domain),
+                None => ("", input.as_str()),
+            };
+
+            if domain.starts_with(REGEX_PREFIX) || domain.contains('*') {
+                return input;
+            }
+
+            let normalized = format!("{}{}", prefix, registrable_domain(domain));
+            if normalized != input {
+                warnings.push(format!("{} is scoped as {}", input, normalized));
+            }
+            normalized
+        })
+        .collect();
+
+    (domains, warnings)
+}
+
+fn host(input: &str) -> String {
+    let input = input.trim();
+    let rest = match input.split_once("://") {
+        Some((_, rest)) => rest,
+        None => input,
+    };
+
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    // IPv6 addresses are bracketed when followed by a port.
+    let host = match host.strip_prefix('[') {
+        Some(address) => address.split(']').next().unwrap_or_default(),
+        None if host.parse::<IpAddr>().is_ok() => host,
+        None => host.split(':').next().unwrap_or_default(),
+    };
+
+    host.trim_end_matches('.').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reduces_urls_to_their_registrable_domain() {
+        assert_eq!(registrable_domain("example.com"), "example.com");
+        assert_eq!(registrable_domain("https://example.com"), "example.com");
+        assert_eq!(registrable_domain("http://example.com:8080"), "example.com");
+        assert_eq!(
+            registrable_domain("https://user@www.shop.example.co.uk/cart?item=1#top"),
+            "example.co.uk"
+        );
+        assert_eq!(
+            registrable_domain("cdn.tracker.example."),
+            "tracker.example"
+        );
+        assert_eq!(registrable_domain("WWW.Example.COM"), "example.com");
+    }
+
+    #[test]
+    fn keeps_hosts_without_registrable_domain() {
+        assert_eq!(registrable_domain("localhost"), "localhost");
+        assert_eq!(registrable_domain("192.168.0.1"), "192.168.0.1");
+        assert_eq!(
+            registrable_domain("http://192.168.0.1:8080/"),
+            "192.168.0.1"
+        );
+        assert_eq!(registrable_domain("::1"), "::1");
+        assert_eq!(registrable_domain("http://[::1]:8080/"), "::1");
+        assert_eq!(registrable_domain("[2001:db8::1]"), "2001:db8::1");
+    }
+
+    /// Observed domains named `names`, with ids in order starting at 1.
+    fn observed(names: &[&str]) -> Vec<Domain> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| Domain {
+                id: index as i64 + 1,
+                name: name.to_string(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn select(patterns: &[&str], names: &[&str]) -> Vec<(i64, String)> {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+
+        Scope::parse(&patterns)
+            .unwrap()
+            .select(observed(names))
+            .into_iter()
+            .map(|domain| (domain.id, domain.name))
+            .collect()
+    }
+
+    fn names(selected: Vec<(i64, String)>) -> Vec<String> {
+        selected.into_iter().map(|(_, name)| name).collect()
+    }
+
+    const OBSERVED: &[&str] = &[
+        "google.com",
+        "google.co.uk",
+        "bbc.co.uk",
+        "cdn12.example",
+        "shop.example",
+        "co.uk",
+    ];
+
+    #[test]
+    fn selects_wildcard_patterns() {
+        assert_eq!(
+            names(select(&["google.*"], OBSERVED)),
+            vec!["google.com", "google.co.uk"]
+        );
+        // A leading `*.` matches the bare domain as well.
+        assert_eq!(
+            names(select(&["*.co.uk"], OBSERVED)),
+            vec!["google.co.uk", "bbc.co.uk", "co.uk"]
+        );
+    }
+
+    #[test]
+    fn selects_regex_patterns() {
+        assert_eq!(
+            names(select(&[r"regex:^cdn\d+\."], OBSERVED)),
+            vec!["cdn12.example"]
+        );
+        assert_eq!(
+            names(select(&["regex:example"], OBSERVED)),
+            vec!["cdn12.example", "shop.example"]
+        );
+    }
+
+    #[test]
+    fn lists_overlapping_patterns_once() {
+        assert_eq!(
+            select(&["bbc.co.uk", "*.co.uk", "regex:co", "bbc.co.uk"], OBSERVED),
+            vec![
+                (3, "bbc.co.uk".to_owned()),
+                (1, "google.com".to_owned()),
+                (2, "google.co.uk".to_owned()),
+                (6, "co.uk".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn excludes_domains_from_patterns_and_exact_domains() {
+        assert_eq!(
+            names(select(
+                &["google.*", "!google.co.uk", "bbc.co.uk", "!bbc.*"],
+                OBSERVED
+            )),
+            vec!["google.com"]
+        );
+        assert_eq!(
+            names(select(&["!*.co.uk", "!regex:example"], OBSERVED)),
+            vec!["google.com"]
+        );
+    }
+
+    #[test]
+    fn selects_normalized_exact_domains_observed_or_not() {
+        let (patterns, warnings) = normalize(vec![
+            "https://www.Google.co.uk/search".to_owned(),
+            "news.example".to_owned(),
+        ]);
+        let scope = Scope::parse(&patterns).unwrap();
+        let selected: Vec<(i64, String)> = scope
+            .select(observed(OBSERVED))
+            .into_iter()
+            .map(|domain| (domain.id, domain.name))
+            .collect();
+
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(
+            selected,
+            vec![
+                (2, "google.co.uk".to_owned()),
+                (0, "news.example".to_owned())
+            ]
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use crate::database::{Check, Domain, DomainInteraction};
+use crate::filter::Filter;
+
+/// Filters toggled with the number keys in the Domains tab.
+pub const CHIPS: [(&str, Check); 4] = [
+    ("prevalent", |d| d.prevalent),
+    ("very prevalent", |d| d.very_prevalent),
+    ("had interaction", |d| d.had_user_interaction),
+    // ITP removes the website data of prevalent domains the user didn't
+    // interact with lately.
+    ("scheduled for removal", |d| {
+        d.prevalent && !d.had_user_interaction
+    }),
+];
+
+/// Where keys are typed in the search bar.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Input {
+    Query,
+    Filter,
+}
+
+/// Incremental search, filter expression and filter chips of the Domains tab.
+#[derive(Debug, Default)]
+pub struct Search {
+    pub query: String,
+    /// Filter expression as typed, applied once parsed into `filter`.
+    pub expression: String,
+    pub filter: Option<Filter>,
+    pub input: Option<Input>,
+    chips: [bool; CHIPS.len()],
+}
+
+impl Search {
+    pub fn toggle(&mut self, chip: usize) {
+        if let Some(active) = self.chips.get_mut(chip) {
+            *active = !*active;
+        }
+    }
+
+    pub fn is_active(&self, chip: usize) -> bool {
+        self.chips.get(chip).copied().unwrap_or(false)
+    }
+
+    pub fn filtering(&self) -> bool {
+        self.filter.is_some() || self.chips.iter().any(|active| *active)
+    }
+
+    /// Whether a domain, out of its details and interactions, passes the
+    /// filter expression and every active chip.
+    pub fn keep(&self, info: &Domain, interaction: &DomainInteraction) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|filter| filter.matches(info, interaction))
+            && CHIPS
+                .iter()
+                .zip(self.chips.iter())
+                .all(|((_, check), active)| !active || check(info))
+    }
+
+    /// Positions of the query characters in `name`, when they all appear in
+    /// order.
+    pub fn matches(&self, name: &str) -> Option<Vec<usize>> {
+        match self.query.is_empty() {
+            true => None,
+            false => fuzzy_match(&self.query, name),
+        }
+    }
+
+    pub fn first_match(&self, domains: &[Domain]) -> Option<usize> {
+        domains
+            .iter()
+            .position(|domain| self.matches(&domain.name).is_some())
+    }
+
+    /// Index of the next domain matching the query after `from`, or before it
+    /// when going backward, wrapping around the list.
+    pub fn next_match(&self, domains: &[Domain], from: usize, forward: bool) -> Option<usize> {
+        let len = domains.len();
+
+        (1..=len)
+            .map(|offset| match forward {
+                true => (from + offset) % len,
+                false => (from + len - offset % len) % len,
+            })
+            .find(|&index| self.matches(&domains[index].name).is_some())
+    }
+}
+
+/// Case insensitive subsequence match, `gapi` matches `googleapis.com`.
+pub fn fuzzy_match(query: &str, name: &str) -> Option<Vec<usize>> {
+    let mut positions = Vec::new();
+    let mut chars = name.chars().enumerate();
+
+    for wanted in query.chars().flat_map(char::to_lowercase) {
+        let (position, _) = chars.find(|(_, c)| c.to_ascii_lowercase() == wanted)?;
+        positions.push(position);
+    }
+
+    Some(positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn domains(names: &[&str]) -> Vec<Domain> {
+        names
+            .iter()
+            .map(|name| Domain {
+                name: name.to_string(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn query(query: &str) -> Search {
+        Search {
+            query: query.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn matches_characters_in_order_regardless_of_case() {
+        assert_eq!(
+            fuzzy_match("gapi", "googleapis.com"),
+            Some(vec![0, 6, 7, 8])
+        );
+        assert_eq!(
+            fuzzy_match("GAPI", "googleapis.com"),
+            Some(vec![0, 6, 7, 8])
+        );
+        assert_eq!(fuzzy_match("ipag", "googleapis.com"), None);
+        assert_eq!(fuzzy_match("", "googleapis.com"), Some(vec![]));
+        assert_eq!(query("").matches("googleapis.com"), None);
+    }
+
+    #[test]
+    fn finds_the_next_match_around_the_list() {
+        let list = domains(&["ads.example", "news.example", "adtrack.example"]);
+        let search = query("ad");
+
+        assert_eq!(search.first_match(&list), Some(0));
+        assert_eq!(search.next_match(&list, 0, true), Some(2));
+        assert_eq!(search.next_match(&list, 2, true), Some(0));
+        assert_eq!(search.next_match(&list, 0, false), Some(2));
+        assert_eq!(search.next_match(&list, 1, false), Some(0));
+        assert_eq!(query("shop").next_match(&list, 0, true), None);
+        assert_eq!(search.next_match(&[], 0, true), None);
+    }
+
+    #[test]
+    fn keeps_domains_passing_every_active_chip() {
+        let mut search = Search::default();
+        let tracker = Domain {
+            prevalent: true,
+            ..Default::default()
+        };
+        let interaction = DomainInteraction::default();
+
+        assert!(!search.filtering());
+        search.toggle(0);
+        assert!(search.filtering() && search.is_active(0));
+        assert!(search.keep(&tracker, &interaction));
+        search.toggle(2);
+        assert!(!search.keep(&tracker, &interaction));
+    }
+}
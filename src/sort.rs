@@ -0,0 +1,243 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::database::{Domain, DomainInteraction};
+
+/// Where the sort of the Domains tab is remembered across restarts.
+const SORT_FILE: &str = "sort.toml";
+
+/// Columns of the Domains table, the domain name always comes first.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    Name,
+    Prevalence,
+    Iframes,
+    Requests,
+    Redirects,
+    LastSeen,
+    LastInteraction,
+}
+
+impl Column {
+    /// Optional columns, shown by default.
+    pub const OPTIONAL: [Column; 6] = [
+        Column::Prevalence,
+        Column::Iframes,
+        Column::Requests,
+        Column::Redirects,
+        Column::LastSeen,
+        Column::LastInteraction,
+    ];
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Column::Name => "DOMAIN",
+            Column::Prevalence => "ITP",
+            Column::Iframes => "IFR",
+            Column::Requests => "REQ",
+            Column::Redirects => "RED",
+            Column::LastSeen => "SEEN",
+            Column::LastInteraction => "INTERACTED",
+        }
+    }
+
+    /// Characters taken by the values of the column, dates being the widest.
+    pub fn width(&self) -> u16 {
+        match self {
+            Column::LastSeen | Column::LastInteraction => 10,
+            _ => 3,
+        }
+    }
+
+    fn compare(
+        &self,
+        (a, a_interaction): (&Domain, &DomainInteraction),
+        (b, b_interaction): (&Domain, &DomainInteraction),
+    ) -> Ordering {
+        let time = |time: Option<f64>| time.unwrap_or(0.0);
+
+        match self {
+            Column::Name => a.name.cmp(&b.name),
+            Column::Prevalence => {
+                (a.very_prevalent, a.prevalent).cmp(&(b.very_prevalent, b.prevalent))
+            }
+            Column::Iframes => a_interaction.iframes.cmp(&b_interaction.iframes),
+            Column::Requests => a_interaction.requests.cmp(&b_interaction.requests),
+            Column::Redirects => a_interaction.redirects.cmp(&b_interaction.redirects),
+            Column::LastSeen => time(a.last_seen).total_cmp(&time(b.last_seen)),
+            Column::LastInteraction => {
+                time(a.last_interaction).total_cmp(&time(b.last_interaction))
+            }
+        }
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "prevalence" => Ok(Column::Prevalence),
+            "iframes" => Ok(Column::Iframes),
+            "requests" => Ok(Column::Requests),
+            "redirects" => Ok(Column::Redirects),
+            "last_seen" => Ok(Column::LastSeen),
+            "last_interaction" => Ok(Column::LastInteraction),
+            _ => Err(format!(
+                "unknown column {}, expected prevalence, iframes, requests, redirects, last_seen or last_interaction",
+                name
+            )),
+        }
+    }
+}
+
+/// Order of the Domains table.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Sort {
+    pub column: Column,
+    pub descending: bool,
+}
+
+impl Default for Sort {
+    fn default() -> Self {
+        Sort {
+            column: Column::Name,
+            descending: false,
+        }
+    }
+}
+
+impl Sort {
+    /// The sort remembered from the last session, by name otherwise.
+    pub fn load() -> Self {
+        sort_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|sort| toml::from_str(&sort).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = sort_path().ok_or("no configuration directory")?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+
+        Ok(())
+    }
+
+    /// Sorts by the column following the current one among `columns`,
+    /// coming back to the name after the last one.
+    pub fn next_column(&mut self, columns: &[Column]) {
+        let position = columns.iter().position(|column| *column == self.column);
+
+        self.column = match position {
+            Some(position) => columns.get(position + 1).copied().unwrap_or(Column::Name),
+            None if self.column == Column::Name => columns.first().copied().unwrap_or(Column::Name),
+            None => Column::Name,
+        };
+    }
+
+    /// Sorts `domains`, along with their interactions, ties ordered by name
+    /// whichever the direction.
+    pub fn apply(&self, domains: &mut [(Domain, DomainInteraction)]) {
+        domains.sort_by(|(a, a_interaction), (b, b_interaction)| {
+            let ordering = self.column.compare((a, a_interaction), (b, b_interaction));
+
+            match self.descending {
+                true => ordering.reverse(),
+                false => ordering,
+            }
+            .then_with(|| a.name.cmp(&b.name))
+        });
+    }
+}
+
+fn sort_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|config| config.join("itp_tldr").join(SORT_FILE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(name: &str, iframes: i32) -> (Domain, DomainInteraction) {
+        (
+            Domain {
+                name: name.to_owned(),
+                ..Default::default()
+            },
+            DomainInteraction {
+                iframes,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn names(rows: &[(Domain, DomainInteraction)]) -> Vec<&str> {
+        rows.iter()
+            .map(|(domain, _)| domain.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn cycles_through_the_visible_columns() {
+        let columns = [Column::Iframes, Column::LastSeen];
+        let mut sort = Sort::default();
+
+        sort.next_column(&columns);
+        assert_eq!(sort.column, Column::Iframes);
+        sort.next_column(&columns);
+        assert_eq!(sort.column, Column::LastSeen);
+        sort.next_column(&columns);
+        assert_eq!(sort.column, Column::Name);
+
+        // A column hidden since it was picked goes back to the name.
+        sort.column = Column::Redirects;
+        sort.next_column(&columns);
+        assert_eq!(sort.column, Column::Name);
+
+        sort.next_column(&[]);
+        assert_eq!(sort.column, Column::Name);
+    }
+
+    #[test]
+    fn orders_ties_by_name() {
+        let mut rows = vec![
+            row("c.example", 1),
+            row("b.example", 2),
+            row("a.example", 1),
+            row("d.example", 2),
+        ];
+        let mut sort = Sort {
+            column: Column::Iframes,
+            descending: false,
+        };
+
+        sort.apply(&mut rows);
+        assert_eq!(
+            names(&rows),
+            ["a.example", "c.example", "b.example", "d.example"]
+        );
+
+        sort.descending = true;
+        sort.apply(&mut rows);
+        assert_eq!(
+            names(&rows),
+            ["b.example", "d.example", "a.example", "c.example"]
+        );
+
+        sort.column = Column::Name;
+        sort.apply(&mut rows);
+        assert_eq!(
+            names(&rows),
+            ["d.example", "c.example", "b.example", "a.example"]
+        );
+    }
+}
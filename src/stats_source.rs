@@ -0,0 +1,122 @@
+use rusqlite::{params, Connection, OpenFlags, Result as SQLiteResult};
+
+use crate::database::{Database, Domain, DomainInteraction};
+use crate::dips::Dips;
+use crate::firefox::Firefox;
+use crate::Config;
+
+const HAS_TABLE: &str = "SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = ?";
+
+/// Everything the UI needs to know about the observed domains, independent of
+/// where the statistics are stored.
+pub trait StatsSource {
+    /// Domains to be listed, honouring any configured scope.
+    fn get_domains(&self) -> SQLiteResult<Vec<Domain>>;
+
+    /// Classification and first party counters of a listed domain.
+    fn get_info(&self, domain: &Domain) -> SQLiteResult<Domain>;
+
+    /// How the domain relates to the other domains it was seen with.
+    fn domain_interaction(&self, domain: &Domain) -> DomainInteraction;
+
+    /// Details and interactions of every listed domain, domains in scope but
+    /// not observed yet (id 0) having none.
+    fn details(&self, domains: Vec<Domain>) -> Vec<(Domain, DomainInteraction)> {
+        domains
+            .into_iter()
+            .map(|domain| match domain.id {
+                0 => (domain, DomainInteraction::default()),
+                _ => {
+                    let interaction = self.domain_interaction(&domain);
+                    (self.get_info(&domain).unwrap_or(domain), interaction)
+                }
+            })
+            .collect()
+    }
+
+    /// The observed domain named `name`.
+    fn find(&self, name: &str) -> Option<Domain> {
+        self.get_domains()
+            .ok()?
+            .into_iter()
+            .find(|domain| domain.id != 0 && domain.name == name)
+    }
+
+    /// Every relationship observed between domains, empty when the database
+    /// doesn't record them.
+    fn relationships(&self) -> Vec<Relationship> {
+        Vec::new()
+    }
+
+    /// What each underlying database knows about the domain, empty unless
+    /// several databases are aggregated.
+    fn breakdown(&self, _domain: &Domain) -> Vec<SourceInfo> {
+        Vec::new()
+    }
+}
+
+/// A domain as seen by one of several aggregated databases, `info` is `None`
+/// when that database never observed it.
+#[derive(Debug, Clone)]
+pub struct SourceInfo {
+    pub label: String,
+    pub info: Option<Domain>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Relation {
+    Iframe,
+    Subresource,
+    Redirect,
+}
+
+impl Relation {
+    /// How `from` relates to `to`, as in "tracker.example iframed under news.example".
+    pub fn verb(&self) -> &str {
+        match self {
+            Relation::Iframe => "iframed under",
+            Relation::Subresource => "loaded under",
+            Relation::Redirect => "redirects to",
+        }
+    }
+
+    /// How `to` relates to `from`, as in "news.example iframes tracker.example".
+    pub fn inverse_verb(&self) -> &str {
+        match self {
+            Relation::Iframe => "iframes",
+            Relation::Subresource => "loads",
+            Relation::Redirect => "redirected from",
+        }
+    }
+}
+
+/// A third party seen under a top frame, or a redirect between top frames.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Relationship {
+    pub relation: Relation,
+    pub from: String,
+    pub to: String,
+}
+
+/// Opens the database at `config.path` with the backend matching its tables,
+/// WebKit's being assumed when none matches.
+pub fn connect(config: Config) -> SQLiteResult<Box<dyn StatsSource>> {
+    let path = config.path.clone().expect("database path to be configured");
+    let tables = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY);
+    let is_table = |table| tables.as_ref().is_ok_and(|c| has_table(c, table));
+
+    if is_table(Dips::TABLE) {
+        Ok(Box::new(Dips::connect(config)?))
+    } else if is_table(Firefox::TABLE) {
+        Ok(Box::new(Firefox::connect(config)?))
+    } else {
+        Ok(Box::new(Database::connect(config)?))
+    }
+}
+
+fn has_table(connection: &Connection, table: &str) -> bool {
+    connection
+        .query_row(HAS_TABLE, params![table], |row| row.get::<_, i64>(0))
+        .map(|count| count > 0)
+        .unwrap_or(false)
+}
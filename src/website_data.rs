@@ -0,0 +1,432 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rusqlite::{Connection, OpenFlags, NO_PARAMS};
+
+use crate::scope;
+
+/// Directories WebKit stores website data in, besides ITP's own, as named by
+/// Safari and by the WebKitGTK and WPE ports respectively.
+const KINDS: &[(&str, Kind)] = &[
+    ("LocalStorage", Kind::LocalStorage),
+    ("localstorage", Kind::LocalStorage),
+    ("IndexedDB", Kind::IndexedDB),
+    ("databases/indexeddb", Kind::IndexedDB),
+    ("ServiceWorkers", Kind::ServiceWorkers),
+    ("serviceworkers", Kind::ServiceWorkers),
+    ("CacheStorage", Kind::CacheStorage),
+];
+/// Newer WebKit versions keep every kind of data of an origin together, under
+/// two levels of salted hashes of its top frame and frame origins.
+const ORIGIN_STORAGE: &str = "Default";
+/// Versions of the legacy IndexedDB layout: `v0` lists origins like the other
+/// kinds, `v1` nests the frame origins under their top frame origin.
+const INDEXEDDB_V0: &str = "v0";
+const INDEXEDDB_V1: &str = "v1";
+const ORIGIN_FILE: &str = "origin";
+const REGISTRATIONS: &str = "SELECT key FROM Records";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    LocalStorage,
+    IndexedDB,
+    ServiceWorkers,
+    CacheStorage,
+}
+
+impl Kind {
+    pub fn name(&self) -> &str {
+        match self {
+            Kind::LocalStorage => "LocalStorage",
+            Kind::IndexedDB => "IndexedDB",
+            Kind::ServiceWorkers => "Service workers",
+            Kind::CacheStorage => "CacheStorage",
+        }
+    }
+}
+
+/// Data stored by an origin, attributed to the registrable domain of the
+/// host it was stored for, the way ITP records domains.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub kind: Kind,
+    pub host: String,
+    pub domain: String,
+    /// Bytes on disk, 0 when it shares a file with other origins.
+    pub size: u64,
+}
+
+impl Entry {
+    fn new(kind: Kind, host: String, size: u64) -> Self {
+        Entry {
+            kind,
+            domain: scope::registrable_domain(&host),
+            host,
+            size,
+        }
+    }
+
+    /// Whether the data was stored for a host whose registrable domain is
+    /// `domain`, as ITP lists domains: `domain` itself or one of its
+    /// subdomains, never its parent domains.
+    pub fn belongs_to(&self, domain: &str) -> bool {
+        self.domain == domain
+    }
+}
+
+/// The website data directory of the ITP database at `database`, the one
+/// holding its `ResourceLoadStatistics` or `itp` directory.
+pub fn locate(database: &Path) -> Option<PathBuf> {
+    database
+        .parent()
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+        .filter(|root| {
+            KINDS.iter().any(|(dir, _)| root.join(dir).is_dir())
+                || root.join(ORIGIN_STORAGE).is_dir()
+        })
+}
+
+/// Every piece of website data under `root`.
+pub fn scan(root: &Path) -> Vec<Entry> {
+    let mut entries = Vec::new();
+
+    for (directory, kind) in KINDS {
+        let directory = root.join(directory);
+
+        match kind {
+            Kind::ServiceWorkers => entries.extend(registrations(&directory)),
+            Kind::IndexedDB => {
+                for path in children(&directory) {
+                    match file_name(&path).as_str() {
+                        INDEXEDDB_V0 => entries.extend(origins(*kind, &path)),
+                        INDEXEDDB_V1 => {
+                            for top_frame in children(&path) {
+                                entries.extend(origins(*kind, &top_frame));
+                            }
+                        }
+                        _ => entries.extend(origin(*kind, &path)),
+                    }
+                }
+            }
+            _ => entries.extend(origins(*kind, &directory)),
+        }
+    }
+
+    for top_frame in children(&root.join(ORIGIN_STORAGE)) {
+        for frame in children(&top_frame) {
+            let host = match origin_host(&frame.join(ORIGIN_FILE)) {
+                Some(host) => host,
+                None => continue,
+            };
+
+            for (directory, kind) in KINDS {
+                let path = frame.join(directory);
+
+                if path.exists() {
+                    entries.push(Entry::new(*kind, host.clone(), size(&path)));
+                }
+            }
+        }
+    }
+
+    entries
+}
+
+/// Data of every origin directory or file under `directory`.
+fn origins(kind: Kind, directory: &Path) -> Vec<Entry> {
+    children(directory)
+        .iter()
+        .filter_map(|path| origin(kind, path))
+        .collect()
+}
+
+/// Data of the origin at `path`, named after it or describing it in an
+/// `origin` file.
+fn origin(kind: Kind, path: &Path) -> Option<Entry> {
+    let host = legacy_host(&file_name(path)).or_else(|| origin_host(&path.join(ORIGIN_FILE)))?;
+
+    Some(Entry::new(kind, host, size(path)))
+}
+
+/// Formats bytes the way Finder does.
+pub fn format_size(bytes: u64) -> String {
+    let units = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1000.0 && unit < units.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+
+    match unit {
+        0 => format!("{} {}", bytes, units[0]),
+        _ => format!("{:.1} {}", size, units[unit]),
+    }
+}
+
+/// Service worker registrations, keyed by their scope, share a database.
+fn registrations(directory: &Path) -> Vec<Entry> {
+    let mut entries = Vec::new();
+
+    for path in children(directory) {
+        if path
+            .extension()
+            .is_none_or(|extension| extension != "sqlite3")
+        {
+            continue;
+        }
+
+        let connection = match Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        {
+            Ok(connection) => connection,
+            Err(_) => continue,
+        };
+        let mut stmt = match connection.prepare(REGISTRATIONS) {
+            Ok(stmt) => stmt,
+            Err(_) => continue,
+        };
+        let keys = stmt
+            .query_map(NO_PARAMS, |row| row.get::<_, String>(0))
+            .map(|rows| rows.filter_map(|r| r.ok()).collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        entries.extend(
+            keys.iter()
+                .filter_map(|key| url_host(key))
+                .map(|host| Entry::new(Kind::ServiceWorkers, host, 0)),
+        );
+    }
+
+    entries
+}
+
+/// Host out of names such as `https_www.example.com_0.localstorage`.
+fn legacy_host(name: &str) -> Option<String> {
+    let name = name.split(".localstorage").next()?;
+    let (scheme, rest) = name.split_once('_')?;
+    let (host, _port) = rest.rsplit_once('_')?;
+
+    match scheme {
+        "http" | "https" => Some(host.to_owned()),
+        _ => None,
+    }
+}
+
+/// Host of the frame origin serialized in an `origin` file, after the top
+/// frame's. Both are stored as their scheme followed by their host.
+fn origin_host(path: &Path) -> Option<String> {
+    let bytes = fs::read(path).ok()?;
+    let strings: Vec<String> = bytes
+        .split(|b| !b.is_ascii_graphic())
+        .filter(|run| !run.is_empty())
+        .map(|run| String::from_utf8_lossy(run).into_owned())
+        .collect();
+
+    strings
+        .windows(2)
+        .rev()
+        .find(|pair| pair[0] == "http" || pair[0] == "https")
+        .map(|pair| pair[1].clone())
+}
+
+fn url_host(url: &str) -> Option<String> {
+    let (_, rest) = url.split_once("://")?;
+    let host = rest.split([':', '/', '_']).next()?;
+
+    match host.is_empty() {
+        true => None,
+        false => Some(host.to_owned()),
+    }
+}
+
+fn size(path: &Path) -> u64 {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => children(path).iter().map(|child| size(child)).sum(),
+        Ok(metadata) => metadata.len(),
+        Err(_) => 0,
+    }
+}
+
+fn children(path: &Path) -> Vec<PathBuf> {
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A website data directory, removed once dropped.
+    struct Root(PathBuf);
+
+    impl Root {
+        fn new(name: &str) -> Self {
+            let path = crate::fixture::temporary_path(name);
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Root(path)
+        }
+
+        /// Writes `bytes` at `relative`, creating the directories leading to it.
+        fn write(&self, relative: &str, bytes: &[u8]) -> &Self {
+            let path = self.0.join(relative);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, bytes).unwrap();
+            self
+        }
+    }
+
+    impl Drop for Root {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Kind, host and size of every entry, in a stable order.
+    fn summary(entries: Vec<Entry>) -> Vec<(&'static str, String, u64)> {
+        let mut summary: Vec<_> = entries
+            .into_iter()
+            .map(|entry| {
+                let kind = match entry.kind {
+                    Kind::LocalStorage => "local",
+                    Kind::IndexedDB => "indexeddb",
+                    Kind::ServiceWorkers => "workers",
+                    Kind::CacheStorage => "cache",
+                };
+                (kind, entry.host, entry.size)
+            })
+            .collect();
+        summary.sort();
+        summary
+    }
+
+    #[test]
+    fn reads_hosts_out_of_origin_strings() {
+        assert_eq!(
+            legacy_host("https_www.news.example_0.localstorage"),
+            Some("www.news.example".to_owned())
+        );
+        assert_eq!(
+            legacy_host("http_news.example_8080"),
+            Some("news.example".to_owned())
+        );
+        assert_eq!(legacy_host("file__0"), None);
+        assert_eq!(legacy_host("v1"), None);
+
+        assert_eq!(
+            url_host("https://push.tracker.example:443/sw.js"),
+            Some("push.tracker.example".to_owned())
+        );
+        assert_eq!(
+            url_host("https://tracker.example/_https://tracker.example/sw.js"),
+            Some("tracker.example".to_owned())
+        );
+        assert_eq!(url_host("https:///"), None);
+        assert_eq!(url_host("tracker.example"), None);
+    }
+
+    #[test]
+    fn scans_the_legacy_layouts() {
+        let root = Root::new("website-data-legacy");
+        root.write(
+            "LocalStorage/https_www.news.example_0.localstorage",
+            &[0; 10],
+        )
+        .write("LocalStorage/StorageTracker.db", &[0; 3])
+        .write("IndexedDB/https_shop.example_0/shop.sqlite3", &[0; 20])
+        .write("IndexedDB/v0/https_blog.example_0/blog.sqlite3", &[0; 30])
+        .write(
+            "IndexedDB/v1/https_news.example_0/https_cdn.tracker.example_0/ads/IndexedDB.sqlite3",
+            &[0; 40],
+        )
+        .write("CacheStorage/https_news.example_0/records", &[0; 5])
+        .write("databases/indexeddb/http_gtk.example_0/db.sqlite3", &[0; 7]);
+
+        assert_eq!(
+            summary(scan(&root.0)),
+            vec![
+                ("cache", "news.example".to_owned(), 5),
+                ("indexeddb", "blog.example".to_owned(), 30),
+                ("indexeddb", "cdn.tracker.example".to_owned(), 40),
+                ("indexeddb", "gtk.example".to_owned(), 7),
+                ("indexeddb", "shop.example".to_owned(), 20),
+                ("local", "www.news.example".to_owned(), 10),
+            ]
+        );
+    }
+
+    #[test]
+    fn scans_the_origin_layout() {
+        let root = Root::new("website-data-origins");
+        // The top frame origin comes first, the frame's last.
+        root.write(
+            "Default/salt1/salt2/origin",
+            b"\x05https\x0cnews.example\x00\x05https\x13cdn.tracker.example\x00",
+        )
+        .write(
+            "Default/salt1/salt2/LocalStorage/localstorage.sqlite3",
+            &[0; 12],
+        )
+        .write("Default/salt1/salt2/CacheStorage/records", &[0; 4])
+        .write(
+            "Default/salt1/salt3/LocalStorage/localstorage.sqlite3",
+            &[0; 8],
+        );
+
+        assert_eq!(
+            summary(scan(&root.0)),
+            vec![
+                ("cache", "cdn.tracker.example".to_owned(), 4),
+                ("local", "cdn.tracker.example".to_owned(), 12),
+            ]
+        );
+    }
+
+    #[test]
+    fn scans_service_worker_registrations() {
+        let root = Root::new("website-data-workers");
+        fs::create_dir_all(root.0.join("ServiceWorkers")).unwrap();
+        let connection = Connection::open(
+            root.0
+                .join("ServiceWorkers/ServiceWorkerRegistrations-7.sqlite3"),
+        )
+        .unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE Records (key TEXT);
+                 INSERT INTO Records VALUES ('https://push.tracker.example/_https://push.tracker.example/sw.js');",
+            )
+            .unwrap();
+        root.write("ServiceWorkers/notes.txt", b"https://news.example/");
+
+        assert_eq!(
+            summary(scan(&root.0)),
+            vec![("workers", "push.tracker.example".to_owned(), 0)]
+        );
+    }
+
+    #[test]
+    fn attributes_data_to_registrable_domains() {
+        let entry = Entry::new(Kind::LocalStorage, "cdn.tracker.co.uk".to_owned(), 1);
+
+        assert_eq!(entry.domain, "tracker.co.uk");
+        assert!(entry.belongs_to("tracker.co.uk"));
+        assert!(!entry.belongs_to("cdn.tracker.co.uk"));
+        assert!(!entry.belongs_to("co.uk"));
+        assert!(!entry.belongs_to("other.co.uk"));
+    }
+}